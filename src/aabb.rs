@@ -0,0 +1,86 @@
+use crate::{ray::Ray, vec3::Point3};
+
+/// An axis-aligned bounding box, used to quickly reject rays in the BVH.
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    minimum: Point3,
+    maximum: Point3
+}
+
+impl Aabb {
+    pub fn new(minimum: Point3, maximum: Point3) -> Self { Self { minimum, maximum } }
+
+    pub fn min(&self) -> Point3 { self.minimum }
+    pub fn max(&self) -> Point3 { self.maximum }
+    pub fn centre(&self) -> Point3 { 0.5 * (self.minimum + self.maximum) }
+
+    /// Returns the eight corners of the box.
+    pub fn corners(&self) -> [Point3; 8] {
+        let (a, b) = (self.minimum, self.maximum);
+        [
+            Point3::new(a.x(), a.y(), a.z()), Point3::new(b.x(), a.y(), a.z()),
+            Point3::new(a.x(), b.y(), a.z()), Point3::new(b.x(), b.y(), a.z()),
+            Point3::new(a.x(), a.y(), b.z()), Point3::new(b.x(), a.y(), b.z()),
+            Point3::new(a.x(), b.y(), b.z()), Point3::new(b.x(), b.y(), b.z()),
+        ]
+    }
+
+    /// The smallest box containing all of the given points, padded slightly so that flat shapes
+    /// (such as axis-aligned triangles) don't produce a box with zero thickness.
+    pub fn from_points(points: &[Point3]) -> Self {
+        let mut minimum = Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut maximum = -minimum;
+        for p in points {
+            for a in 0..3 {
+                minimum[a] = minimum[a].min(p[a]);
+                maximum[a] = maximum[a].max(p[a]);
+            }
+        }
+        for a in 0..3 {
+            let pad = 1e-4_f32.max(minimum[a].abs().max(maximum[a].abs()) * 1e-5);
+            if maximum[a] - minimum[a] < pad {
+                minimum[a] -= pad;
+                maximum[a] += pad;
+            }
+        }
+        Self { minimum, maximum }
+    }
+
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.interval(r, t_min, t_max).is_some()
+    }
+
+    /// The range of `t` (clipped to `t_min..t_max`) for which the ray lies inside the box.
+    pub fn interval(&self, r: &Ray, mut t_min: f32, mut t_max: f32) -> Option<(f32, f32)> {
+        let (orig, dir) = (r.origin(), r.direction());
+        for a in 0..3 {
+            let inv_d = 1.0 / dir[a];
+            let mut t0 = (self.minimum[a] - orig[a]) * inv_d;
+            let mut t1 = (self.maximum[a] - orig[a]) * inv_d;
+            if inv_d < 0.0 { std::mem::swap(&mut t0, &mut t1); }
+
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max <= t_min { return None }
+        }
+        Some((t_min, t_max))
+    }
+}
+
+pub fn surrounding_box(box0: &Aabb, box1: &Aabb) -> Aabb {
+    let (a, b) = (box0.min(), box1.min());
+    let (c, d) = (box0.max(), box1.max());
+    Aabb::new(
+        Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+        Point3::new(c.x().max(d.x()), c.y().max(d.y()), c.z().max(d.z()))
+    )
+}
+
+/// The region common to both boxes, or `None` if they don't overlap.
+pub fn overlapping_box(box0: &Aabb, box1: &Aabb) -> Option<Aabb> {
+    let (a, b) = (box0.min(), box1.min());
+    let (c, d) = (box0.max(), box1.max());
+    let min = Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+    let max = Point3::new(c.x().min(d.x()), c.y().min(d.y()), c.z().min(d.z()));
+    (min.x() <= max.x() && min.y() <= max.y() && min.z() <= max.z()).then(|| Aabb::new(min, max))
+}
@@ -0,0 +1,155 @@
+// The shape of a camera's aperture, which sets the shape of out-of-focus highlights ("bokeh").
+//
+// Each shape is sampled uniformly over its area, within the unit disk (or, for an image mask, the
+// square around it), and then scaled by the lens radius.
+
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+use crate::texture::ImageTexture;
+use crate::util::degrees_to_radians;
+use crate::vec3::sample_in_unit_disk;
+
+/// A grid of probabilities over an image, from which pixels can be drawn in proportion to their
+/// brightness.
+#[derive(Clone, Debug)]
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    rows: Vec<f32>,    // The cumulative distribution over rows, from the top, starting at 0.
+    columns: Vec<f32>  // The cumulative distribution over each row's pixels, row by row.
+}
+
+/// The index `i` with `cdf[i] <= u < cdf[i + 1]`, and how far `u` lies between the two.
+fn find_interval(cdf: &[f32], u: f32) -> (usize, f32) {
+    let i = cdf.partition_point(|&c| c <= u).clamp(1, cdf.len() - 1) - 1;
+    let width = cdf[i + 1] - cdf[i];
+    (i, if width > 0.0 { ((u - cdf[i]) / width).clamp(0.0, 1.0) } else { 0.5 })
+}
+
+/// Normalises running totals into a distribution, or makes it uniform if they're all zero.
+fn normalise(cdf: &mut [f32]) {
+    let (total, n) = (cdf[cdf.len() - 1], (cdf.len() - 1) as f32);
+    for (i, c) in cdf.iter_mut().enumerate() {
+        *c = if total > 0.0 { *c / total } else { i as f32 / n };
+    }
+}
+
+impl ApertureMask {
+    /// A mask where the aperture is open in proportion to the brightness of `image`, which covers
+    /// the square around the lens.
+    pub fn new(image: &ImageTexture) -> Self {
+        let (width, height) = (image.width(), image.height());
+        let mut columns = Vec::with_capacity(height * (width + 1));
+        let mut rows = vec![0.0];
+        for y in 0..height {
+            let start = columns.len();
+            columns.push(0.0);
+            let mut total = 0.0;
+            for x in 0..width {
+                let (u, v) = ((x as f32 + 0.5) / width as f32, 1.0 - (y as f32 + 0.5) / height as f32);
+                total += image.value(u, v).max(0.0);
+                columns.push(total);
+            }
+            normalise(&mut columns[start..]);
+            rows.push(rows[y] + total);
+        }
+        assert!(rows[height] > 0.0, "An aperture mask must have some open area.");
+        normalise(&mut rows);
+        Self { width, height, rows, columns }
+    }
+
+    /// A point in [-1, 1]², drawn in proportion to the mask's brightness.
+    fn sample(&self, (u1, u2): (f32, f32)) -> (f32, f32) {
+        let (y, fy) = find_interval(&self.rows, u2);
+        let row = &self.columns[y * (self.width + 1)..(y + 1) * (self.width + 1)];
+        let (x, fx) = find_interval(row, u1);
+        let px = (x as f32 + fx) / self.width as f32;
+        let py = (y as f32 + fy) / self.height as f32;
+        (2.0 * px - 1.0, 1.0 - 2.0 * py)
+    }
+
+    /// Whether the mask is open at `(x, y)` in [-1, 1]².
+    fn contains(&self, x: f32, y: f32) -> bool {
+        if x.abs() > 1.0 || y.abs() > 1.0 { return false }
+        let column = (((x + 1.0) / 2.0 * self.width as f32) as usize).min(self.width - 1);
+        let row = (((1.0 - y) / 2.0 * self.height as f32) as usize).min(self.height - 1);
+        let columns = &self.columns[row * (self.width + 1)..];
+        self.rows[row + 1] > self.rows[row] && columns[column + 1] > columns[column]
+    }
+}
+
+/// The shape of a lens's opening.
+#[derive(Clone, Debug, Default)]
+pub enum Aperture {
+    /// A perfect circle, giving round highlights.
+    #[default]
+    Circle,
+    /// A regular polygon inscribed in the circle, as formed by a diaphragm of `blades` straight
+    /// blades, turned `rotation` degrees anticlockwise from having a corner at the right.
+    Polygon { blades: usize, rotation: f32 },
+    /// An arbitrary shape from an image (e.g. a star or heart cut from card).
+    Mask(ApertureMask)
+}
+
+/// Parses `circle`, a number of blades (e.g. `6`, or `6:15` to turn it by 15 degrees), or else
+/// the path of an image to use as a mask.
+impl FromStr for Aperture {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "circle" { return Ok(Aperture::Circle) }
+        let (blades, rotation) = s.split_once(':').unwrap_or((s, "0"));
+        if let (Ok(blades), Ok(rotation)) = (blades.parse::<usize>(), rotation.parse::<f32>()) {
+            if blades < 3 { return Err(format!("An aperture needs at least 3 blades, not {blades}.")) }
+            return Ok(Aperture::Polygon { blades, rotation })
+        }
+        let image = ImageTexture::load(s).map_err(|e| format!("Couldn't load aperture mask {s}: {e}"))?;
+        Ok(Aperture::Mask(ApertureMask::new(&image)))
+    }
+}
+
+impl Aperture {
+    /// A point on the aperture, uniformly distributed over its open area, relative to the lens
+    /// centre and scaled so that the lens has radius 1.
+    pub fn sample(&self, u: (f32, f32)) -> (f32, f32) {
+        match self {
+            Aperture::Circle => {
+                let p = sample_in_unit_disk(u);
+                (p.x(), p.y())
+            }
+            Aperture::Polygon { blades, rotation } => {
+                let n = (*blades).max(3);
+                // The first component picks one of the triangles fanning out from the centre, and
+                // what's left of it picks a point within that triangle:
+                let scaled = u.0 * n as f32;
+                let k = (scaled as usize).min(n - 1);
+                let (s, t) = ((scaled - k as f32).sqrt(), u.1);
+
+                let corner = |i: usize| {
+                    let angle = degrees_to_radians(*rotation) + 2.0 * PI * i as f32 / n as f32;
+                    (angle.cos(), angle.sin())
+                };
+                let (a, b) = (corner(k), corner(k + 1));
+                (s * ((1.0 - t) * a.0 + t * b.0), s * ((1.0 - t) * a.1 + t * b.1))
+            }
+            Aperture::Mask(mask) => mask.sample(u)
+        }
+    }
+
+    /// Whether the aperture is open at `(x, y)`, relative to the lens centre and scaled so that
+    /// the lens has radius 1.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        match self {
+            Aperture::Circle => x * x + y * y <= 1.0,
+            Aperture::Polygon { blades, rotation } => {
+                // Fold the angle into the sector of one side, and compare against the distance
+                // from the centre to that side:
+                let half_sector = PI / (*blades).max(3) as f32;
+                let angle = (y.atan2(x) - degrees_to_radians(*rotation)).rem_euclid(2.0 * half_sector) - half_sector;
+                (x * x + y * y).sqrt() * angle.cos() <= half_sector.cos()
+            }
+            Aperture::Mask(mask) => mask.contains(x, y)
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::bvh::BvhNode;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::*;
+
+/// The control points of a bicubic patch, indexed `[u][v]`.
+pub type ControlPoints = [[Point3; 4]; 4];
+
+/// The cubic Bernstein polynomials, and their derivatives, at `t`.
+fn bernstein(t: f32) -> ([f32; 4], [f32; 4]) {
+    let s = 1.0 - t;
+    (
+        [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t],
+        [-3.0 * s * s, 3.0 * s * s - 6.0 * t * s, 6.0 * t * s - 3.0 * t * t, 3.0 * t * t]
+    )
+}
+
+/// Evaluates the surface and its partial derivatives at `(u, v)`.
+fn evaluate(cp: &ControlPoints, u: f32, v: f32) -> (Point3, Vec3, Vec3) {
+    let ((bu, dbu), (bv, dbv)) = (bernstein(u), bernstein(v));
+    let (mut p, mut du, mut dv) = (Point3::default(), Vec3::default(), Vec3::default());
+    for i in 0..4 {
+        for j in 0..4 {
+            p += bu[i] * bv[j] * cp[i][j];
+            du += dbu[i] * bv[j] * cp[i][j];
+            dv += bu[i] * dbv[j] * cp[i][j];
+        }
+    }
+    (p, du, dv)
+}
+
+/// Splits a cubic curve in half with de Casteljau's algorithm.
+fn split_curve(c: [Point3; 4]) -> ([Point3; 4], [Point3; 4]) {
+    let mid = |a: Point3, b: Point3| 0.5 * (a + b);
+    let (ab, bc, cd) = (mid(c[0], c[1]), mid(c[1], c[2]), mid(c[2], c[3]));
+    let (abc, bcd) = (mid(ab, bc), mid(bc, cd));
+    let centre = mid(abc, bcd);
+    ([c[0], ab, abc, centre], [centre, bcd, cd, c[3]])
+}
+
+fn split_u(cp: &ControlPoints) -> (ControlPoints, ControlPoints) {
+    let (mut lo, mut hi) = (*cp, *cp);
+    for j in 0..4 {
+        let (a, b) = split_curve([cp[0][j], cp[1][j], cp[2][j], cp[3][j]]);
+        for i in 0..4 { (lo[i][j], hi[i][j]) = (a[i], b[i]); }
+    }
+    (lo, hi)
+}
+
+fn split_v(cp: &ControlPoints) -> (ControlPoints, ControlPoints) {
+    let (mut lo, mut hi) = (*cp, *cp);
+    for i in 0..4 { (lo[i], hi[i]) = split_curve(cp[i]); }
+    (lo, hi)
+}
+
+struct Surface {
+    cp: ControlPoints,
+    material: Arc<dyn Material>
+}
+
+/// A piece of a patch covering `u0..u1` by `v0..v1`, bounded by its own control points (which, by
+/// the convex hull property, contain that part of the surface).
+struct PatchPiece {
+    surface: Arc<Surface>,
+    u: (f32, f32), v: (f32, f32),
+    bbox: Aabb
+}
+
+impl Hittable for PatchPiece {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        if !self.bbox.hit(r, t_min, t_max) { return None }
+
+        // Represent the ray as the intersection of two planes, then use Newton's method to find
+        // where the surface meets both of them, starting from the centre of this piece:
+        let d = r.direction();
+        let n1 = unit_vector(&if d.x().abs() > d.y().abs() && d.x().abs() > d.z().abs() {
+            Vec3::new(d.y(), -d.x(), 0.0)
+        } else { Vec3::new(0.0, d.z(), -d.y()) });
+        let n2 = unit_vector(&cross(&d, &n1));
+        let (d1, d2) = (dot(&n1, &r.origin()), dot(&n2, &r.origin()));
+
+        let cp = &self.surface.cp;
+        let (mut u, mut v) = (0.5 * (self.u.0 + self.u.1), 0.5 * (self.v.0 + self.v.1));
+        let tolerance = 1e-5 * (self.bbox.max() - self.bbox.min()).length().max(1e-3);
+        let mut converged = false;
+
+        for _ in 0..8 {
+            let (p, du, dv) = evaluate(cp, u, v);
+            let f = (dot(&n1, &p) - d1, dot(&n2, &p) - d2);
+            if f.0.abs() < tolerance && f.1.abs() < tolerance {
+                converged = true;
+                break
+            }
+
+            let (a, b, c, e) = (dot(&n1, &du), dot(&n1, &dv), dot(&n2, &du), dot(&n2, &dv));
+            let det = a * e - b * c;
+            if det.abs() < 1e-12 { return None }
+            u -= (e * f.0 - b * f.1) / det;
+            v -= (a * f.1 - c * f.0) / det;
+        }
+
+        let slack = 1e-3;
+        let in_piece = |x: f32, (lo, hi): (f32, f32)| lo - slack <= x && x <= hi + slack;
+        if !converged || !in_piece(u, self.u) || !in_piece(v, self.v) { return None }
+        let (u, v) = (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0));
+
+        let (p, du, dv) = evaluate(cp, u, v);
+        let t = dot(&(p - r.origin()), &d) / d.length_squared();
+        if t < t_min || t_max < t { return None }
+
+        // Some patches (e.g. at the tip of the teapot's lid) collapse an edge to a point, leaving
+        // the normal undefined there; nudge towards the middle of the patch in that case:
+        let mut normal = cross(&du, &dv);
+        if normal.length_squared() < 1e-12 {
+            let (_, du, dv) = evaluate(cp, u + 1e-3 * (0.5 - u).signum(), v + 1e-3 * (0.5 - v).signum());
+            normal = cross(&du, &dv);
+        }
+        let normal = unit_vector(&normal);
+
+        let mut rec = HitRecord::new(p, normal, self.surface.material.clone(), t, true);
+        rec.set_face_normal(r, &normal);
+        (rec.u, rec.v, rec.tangent) = (u, v, du);
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> { Some(self.bbox) }
+}
+
+/// A bicubic Bézier patch, intersected directly rather than tessellated.
+///
+/// The patch is split into `4^depth` pieces at load time; a BVH over their control point bounds
+/// narrows down where each ray could hit, and Newton iteration then finds the exact point.
+pub struct BezierPatch { pieces: BvhNode }
+
+impl BezierPatch {
+    pub fn new(cp: ControlPoints, depth: usize, material: Arc<dyn Material>) -> Self {
+        let surface = Arc::new(Surface { cp, material });
+        let mut pieces = vec![];
+        subdivide(&surface, &cp, (0.0, 1.0), (0.0, 1.0), depth, &mut pieces);
+        Self { pieces: BvhNode::new(pieces) }
+    }
+}
+
+fn subdivide(surface: &Arc<Surface>, cp: &ControlPoints, u: (f32, f32), v: (f32, f32), depth: usize, out: &mut Vec<Arc<dyn Hittable>>) {
+    if depth == 0 {
+        let bbox = Aabb::from_points(&cp.concat());
+        out.push(Arc::new(PatchPiece { surface: surface.clone(), u, v, bbox }));
+        return
+    }
+
+    let (um, vm) = (0.5 * (u.0 + u.1), 0.5 * (v.0 + v.1));
+    let (lo, hi) = split_u(cp);
+    for (half, u) in [(lo, (u.0, um)), (hi, (um, u.1))] {
+        let (a, b) = split_v(&half);
+        subdivide(surface, &a, u, (v.0, vm), depth - 1, out);
+        subdivide(surface, &b, u, (vm, v.1), depth - 1, out);
+    }
+}
+
+impl Hittable for BezierPatch {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> { self.pieces.hit(r, t_min, t_max) }
+    fn bounding_box(&self) -> Option<Aabb> { self.pieces.bounding_box() }
+}
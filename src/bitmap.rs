@@ -1,4 +1,38 @@
-use std::io::Write;
+use std::{fs, io};
+use std::io::{Read, Write};
+
+use crate::vec3::Colour;
+
+/// Reads a PNG as its width, height and pixel colours from 0 to 1 (row by row, from the top),
+/// keeping the full precision of 16-bit images. Greyscale images repeat their one channel in
+/// each colour; alpha is ignored.
+pub fn read_png(fp: &str) -> io::Result<(usize, usize, Vec<Colour>)> {
+    let mut decoder = png::Decoder::new(fs::File::open(fp)?);
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(io::Error::other)?;
+
+    let (colour_type, bit_depth) = reader.output_color_type();
+    let samples = colour_type.samples();
+    let (bytes_per_sample, max) = match bit_depth {
+        png::BitDepth::Sixteen => (2, u16::MAX as f32),
+        _ => (1, u8::MAX as f32),
+    };
+    let sample = |i: usize| if bytes_per_sample == 2 {
+        u16::from_be_bytes([buf[2 * i], buf[2 * i + 1]]) as f32 / max
+    } else { buf[i] as f32 / max };
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let pixels = (0..width * height).map(|p| match colour_type {
+        png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => {
+            let g = sample(p * samples);
+            Colour::new(g, g, g)
+        }
+        _ => Colour::new(sample(p * samples), sample(p * samples + 1), sample(p * samples + 2)),
+    }).collect();
+    Ok((width, height, pixels))
+}
 
 #[derive(Debug, Clone)]
 pub struct Bitmap{ data: Vec<[u8; 3]>, width: usize} 
@@ -8,12 +42,68 @@ impl Bitmap {
         Self { data, width }
     }
 
-    pub fn push_pixel(&mut self, rgb: [u8; 3]) {
-        self.data.push(rgb);
+    pub fn width(&self) -> usize { self.width }
+    pub fn height(&self) -> usize { self.data.len() / self.width }
+
+    /// The pixel in column `x` of row `y`, counting rows from the top.
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] { self.data[y * self.width + x] }
+
+    /// Copies `other` over this image, with its top-left corner at `(x0, y0)`. Any of it which
+    /// falls outside this image is left out.
+    pub fn paste(&mut self, other: &Bitmap, x0: usize, y0: usize) {
+        for y in 0..other.height().min(self.height().saturating_sub(y0)) {
+            for x in 0..other.width().min(self.width.saturating_sub(x0)) {
+                self.data[(y0 + y) * self.width + x0 + x] = other.pixel(x, y);
+            }
+        }
     }
 
-    pub fn push_slice(&mut self, pixels: &[[u8; 3]]) {
-        self.data.extend(pixels)
+    /// Reads an uncompressed 8 (paletted), 24 or 32-bit bitmap.
+    pub fn read(mut in_stream: impl Read) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let mut bytes = vec![];
+        in_stream.read_to_end(&mut bytes)?;
+        if bytes.len() < 54 || &bytes[0..2] != b"BM" { return Err(invalid("Not a bitmap file.")) }
+
+        let u32_at = |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let offset = u32_at(10) as usize;
+        let header_size = u32_at(14) as usize;
+        let width = u32_at(18) as i32;
+        let height = u32_at(22) as i32;
+        let bpp = u16::from_le_bytes([bytes[28], bytes[29]]);
+        if u32_at(30) != 0 { return Err(invalid("Compressed bitmaps are not supported.")) }
+        if width <= 0 { return Err(invalid("Bitmap has no width.")) }
+
+        if offset < 14 + header_size || offset > bytes.len() { return Err(invalid("Bad pixel data offset.")) }
+        let palette: Vec<[u8; 3]> = bytes[14 + header_size..offset].chunks_exact(4)
+            .map(|c| [c[2], c[1], c[0]])
+            .collect();
+        if ![8, 24, 32].contains(&bpp) { return Err(invalid("Unsupported bit depth.")) }
+        let (width, rows) = (width as usize, height.unsigned_abs() as usize);
+        // The sizes come from the header, so may be large enough to overflow:
+        let row_length = width.checked_mul(bpp as usize).map(|bits| (bits / 8).div_ceil(4) * 4);
+        let end = row_length.and_then(|n| n.checked_mul(rows)).and_then(|n| n.checked_add(offset));
+        let row_length = match (row_length, end) {
+            (Some(row_length), Some(end)) if end <= bytes.len() => row_length,
+            _ => return Err(invalid("Bitmap is truncated."))
+        };
+
+        let mut data = Vec::with_capacity(width * rows);
+        for y in 0..rows {
+            // Rows are stored bottom-up, unless the height is negative:
+            let row = if height > 0 { rows - 1 - y } else { y };
+            let start = offset + row * row_length;
+            let pixels = &bytes[start..start + row_length];
+            for x in 0..width {
+                data.push(match bpp {
+                    8 => *palette.get(pixels[x] as usize).ok_or_else(|| invalid("Bad palette index."))?,
+                    24 => [pixels[3 * x + 2], pixels[3 * x + 1], pixels[3 * x]],
+                    32 => [pixels[4 * x + 2], pixels[4 * x + 1], pixels[4 * x]],
+                    _ => return Err(invalid("Unsupported bit depth.")),
+                });
+            }
+        }
+        Ok(Self { data, width })
     }
 
     fn generate_header(&self, img_size: i32, img_width: i32, img_height: i32) -> [u8; 54] {
@@ -44,7 +134,7 @@ impl Bitmap {
     }
 
     pub fn output(&self, mut out_stream: impl Write) -> std::io::Result<()> {
-        let padding = vec![0; (self.width * 3) % 4];
+        let padding = vec![0; (4 - (self.width * 3) % 4) % 4];
         let row_length = (self.width * 3 + padding.len()) as i32;
         let nrow = (self.data.len() / self.width) as i32;
         
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use crate::hittable::HitRecord;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::ImageTexture;
+use crate::vec3::*;
+
+/// The hit as though the surface faced `normal`. Normals which would face away from the incoming
+/// ray are ignored, to avoid light leaking through the surface.
+fn with_normal(r_in: &Ray, rec: &HitRecord, normal: Vec3) -> HitRecord {
+    let mut shaded = rec.clone();
+    if dot(&normal, &r_in.direction()) < 0.0 { shaded.normal = normal }
+    shaded
+}
+
+/// Adds detail to another material with a tangent-space normal map: red, green and blue give
+/// the normal's components along the surface's tangent, bitangent and normal respectively.
+pub struct NormalMap {
+    material: Arc<dyn Material>,
+    map: ImageTexture
+}
+
+impl NormalMap {
+    pub fn new(material: Arc<dyn Material>, map: ImageTexture) -> Self {
+        Self { material, map }
+    }
+}
+
+impl Material for NormalMap {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<(Ray, Colour)> {
+        self.material.scatter(r_in, &with_normal(r_in, rec, self.shading_normal(rec)), sampler)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        self.material.scattering_pdf(r_in, &with_normal(r_in, rec, self.shading_normal(rec)), scattered)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Colour { self.material.emitted(rec) }
+    fn albedo(&self, rec: &HitRecord) -> Colour { self.material.albedo(rec) }
+
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        let (t, b, n) = rec.tangent_frame();
+        let c = 2.0 * self.map.sample(rec.u, rec.v) - Vec3::new(1.0, 1.0, 1.0);
+        unit_vector(&(c.x() * t + c.y() * b + c.z() * n))
+    }
+}
+
+/// Adds detail to another material by tilting its normal according to the slope of a height
+/// map. `strength` is the height represented by a value of 1, in units of texture coordinates.
+pub struct BumpMap {
+    material: Arc<dyn Material>,
+    heights: ImageTexture,
+    strength: f32
+}
+
+impl BumpMap {
+    pub fn new(material: Arc<dyn Material>, heights: ImageTexture, strength: f32) -> Self {
+        Self { material, heights, strength }
+    }
+}
+
+impl Material for BumpMap {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<(Ray, Colour)> {
+        self.material.scatter(r_in, &with_normal(r_in, rec, self.shading_normal(rec)), sampler)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        self.material.scattering_pdf(r_in, &with_normal(r_in, rec, self.shading_normal(rec)), scattered)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Colour { self.material.emitted(rec) }
+    fn albedo(&self, rec: &HitRecord) -> Colour { self.material.albedo(rec) }
+
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        // Central differences, one texel either side:
+        let (du, dv) = (1.0 / self.heights.width() as f32, 1.0 / self.heights.height() as f32);
+        let h = |u: f32, v: f32| self.heights.value(u, v);
+        let dh_du = (h(rec.u + du, rec.v) - h(rec.u - du, rec.v)) / (2.0 * du);
+        let dh_dv = (h(rec.u, rec.v + dv) - h(rec.u, rec.v - dv)) / (2.0 * dv);
+
+        let (t, b, n) = rec.tangent_frame();
+        unit_vector(&(n - self.strength * (dh_du * t + dh_dv * b)))
+    }
+}
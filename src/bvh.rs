@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use crate::aabb::{Aabb, surrounding_box};
+use crate::hittable::*;
+use crate::ray::Ray;
+
+/// A node of a bounding volume hierarchy.
+///
+/// Objects are split along the longest axis of their combined bounds at each level, so building
+/// the same list of objects always produces the same tree.
+pub struct BvhNode {
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+    bbox: Aabb
+}
+
+impl BvhNode {
+    /// Builds a hierarchy over `objects`, which must all be bounded and must not be empty.
+    pub fn new(mut objects: Vec<Arc<dyn Hittable>>) -> Self {
+        assert!(!objects.is_empty(), "Cannot build a BVH with no objects.");
+        let boxes: Vec<Aabb> = objects.iter()
+            .map(|o| o.bounding_box().expect("Unbounded object in BVH."))
+            .collect();
+        let bounds = boxes[1..].iter().fold(boxes[0], |acc, b| surrounding_box(&acc, b));
+
+        let (left, right): (Arc<dyn Hittable>, Arc<dyn Hittable>) = match objects.len() {
+            1 => (objects[0].clone(), objects[0].clone()),
+            2 => (objects[0].clone(), objects[1].clone()),
+            _ => {
+                let extent = bounds.max() - bounds.min();
+                let axis = if extent.x() > extent.y() && extent.x() > extent.z() { 0 }
+                    else if extent.y() > extent.z() { 1 } else { 2 };
+
+                let mut keyed: Vec<_> = objects.drain(..).zip(boxes)
+                    .map(|(o, b)| (b.centre()[axis], o))
+                    .collect();
+                keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+                let rest = keyed.split_off(keyed.len() / 2);
+                let halves = [keyed, rest].map(|h| h.into_iter().map(|(_, o)| o).collect::<Vec<_>>());
+                let [l, r] = halves;
+                (Arc::new(BvhNode::new(l)), Arc::new(BvhNode::new(r)))
+            }
+        };
+
+        Self { left, right, bbox: bounds }
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        if !self.bbox.hit(r, t_min, t_max) { return None }
+
+        let hit_left = self.left.hit(r, t_min, t_max);
+        let t_max = hit_left.as_ref().map_or(t_max, |h| h.t);
+        self.right.hit(r, t_min, t_max).or(hit_left)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> { Some(self.bbox) }
+}
@@ -1,25 +1,52 @@
-use crate::{Vec3, Point3, ray::Ray, util::degrees_to_radians, vec3::{unit_vector, cross, random_in_unit_disk}};
+use std::f32::consts::PI;
+use std::str::FromStr;
 
-pub struct Camera {
+use crate::{Vec3, Point3, aperture::Aperture, hittable::Hittable, ray::Ray, sampler::{Sampler, SamplerType}, util::degrees_to_radians, vec3::{dot, unit_vector, cross}};
+
+pub trait Camera: Sync + Send {
+    /// The ray through `(u, v)` on the image, each from 0 to 1 (with `v` increasing upwards),
+    /// with any random choices drawn from `sampler`. Returns `None` where the camera sees nothing
+    /// (e.g. outside a fisheye lens's image circle).
+    fn get_ray(&self, u: f32, v: f32, sampler: &mut dyn Sampler) -> Option<Ray>;
+}
+
+/// The orthonormal basis of a camera at `look_from` facing `look_at`: `u` to the right, `v` up
+/// and `w` backwards.
+pub(crate) fn look_basis(look_from: Point3, look_at: Point3, vup: Vec3) -> (Vec3, Vec3, Vec3) {
+    let w = unit_vector(&(look_from - look_at));
+    let u = unit_vector(&cross(&vup, &w));
+    (u, cross(&w, &u), w)
+}
+
+/// A time between the shutter opening and closing, drawn from `sampler`.
+pub(crate) fn shutter_time(time0: f32, time1: f32, sampler: &mut dyn Sampler) -> f32 {
+    time0 + sampler.get_1d() * (time1 - time0)
+}
+
+/// A thin-lens perspective camera, with depth of field.
+pub struct PerspectiveCamera {
     origin: Point3,
     lower_left_corner: Point3,
     horizontal: Vec3,
     vertical: Vec3,
     u: Vec3, v: Vec3, w: Vec3,
-    lens_radius: f32
+    lens_radius: f32,
+    aperture: Aperture,
+    vignetting: f32,
+    aspect_ratio: f32,
+    time0: f32, time1: f32 // Shutter open/close times.
 }
 
-impl Camera {
-    pub fn new(look_from: Point3, look_at: Point3, vup: Vec3, vertical_fov: f32, aspect_ratio: f32, aperture: f32, focus_dist: f32) -> Self {
+impl PerspectiveCamera {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(look_from: Point3, look_at: Point3, vup: Vec3, vertical_fov: f32, aspect_ratio: f32, aperture: f32, focus_dist: f32, time0: f32, time1: f32) -> Self {
         let theta = degrees_to_radians(vertical_fov);
         let h = (theta / 2.0).tan();
         let viewport_height = 2.0 * h;
         let viewport_width = aspect_ratio * viewport_height;
 
-        let w = unit_vector(&(look_from - look_at));
-        let u = unit_vector(&cross(&vup, &w));
-        let v = cross(&w, &u);
-        
+        let (u, v, w) = look_basis(look_from, look_at, vup);
+
         let horizontal = focus_dist * viewport_width * u;
         let vertical = focus_dist * viewport_height * v;
 
@@ -29,17 +56,351 @@ impl Camera {
             vertical,
             lower_left_corner: look_from - horizontal/2.0 - vertical / 2.0 - focus_dist * w,
             u, v, w,
-            lens_radius: aperture / 2.0
+            lens_radius: aperture / 2.0,
+            aperture: Aperture::Circle,
+            vignetting: 0.0,
+            aspect_ratio,
+            time0, time1
         }
     }
 
-    pub fn get_ray(&self, u: f32, v: f32) -> Ray {
-        let rd = self.lens_radius * random_in_unit_disk();
-        let offset = self.u * rd.x() + self.v * rd.y();
+    /// Gives the lens an aperture of the given shape, which out-of-focus highlights take on.
+    pub fn with_aperture(mut self, aperture: Aperture) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    /// Adds optical vignetting: the lens barrel clips the aperture seen from away from the centre
+    /// of the image, so highlights there become "cat's eyes" and the corners darken. The barrel's
+    /// opening is as wide as the aperture, offset from it by `strength` lens radii at the corners.
+    pub fn with_vignetting(mut self, strength: f32) -> Self {
+        self.vignetting = strength;
+        self
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    /// The ray through `(u, v)` on the viewport, with the point on the lens and the time drawn
+    /// from `sampler`.
+    fn get_ray(&self, u: f32, v: f32, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let (x, y) = self.aperture.sample(sampler.get_2d());
+        if self.vignetting > 0.0 {
+            // The position on the image, scaled so the corners are 1 from the centre:
+            let scale = self.vignetting / (self.aspect_ratio * self.aspect_ratio + 1.0).sqrt();
+            let (sx, sy) = ((2.0 * u - 1.0) * self.aspect_ratio * scale, (2.0 * v - 1.0) * scale);
+            if (x - sx).powi(2) + (y - sy).powi(2) > 1.0 { return None }
+        }
+        let offset = self.u * (self.lens_radius * x) + self.v * (self.lens_radius * y);
 
-        Ray::new(
-            self.origin + offset, 
-            self.lower_left_corner + u * self.horizontal + v * self.vertical - self.origin - offset
-        )
+        Some(Ray::new(
+            self.origin + offset,
+            self.lower_left_corner + u * self.horizontal + v * self.vertical - self.origin - offset,
+            shutter_time(self.time0, self.time1, sampler)
+        ))
     }
-}
\ No newline at end of file
+}
+
+/// A camera whose rays are all parallel, so that objects appear the same size at any distance,
+/// as in a technical drawing.
+pub struct OrthographicCamera {
+    lower_left_corner: Point3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    direction: Vec3,
+    time0: f32, time1: f32
+}
+
+impl OrthographicCamera {
+    /// A camera looking from `look_from` towards `look_at`, seeing a window `view_height` units
+    /// high.
+    pub fn new(look_from: Point3, look_at: Point3, vup: Vec3, view_height: f32, aspect_ratio: f32, time0: f32, time1: f32) -> Self {
+        let (u, v, w) = look_basis(look_from, look_at, vup);
+        let horizontal = aspect_ratio * view_height * u;
+        let vertical = view_height * v;
+        Self { lower_left_corner: look_from - horizontal / 2.0 - vertical / 2.0, horizontal, vertical, direction: -w, time0, time1 }
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn get_ray(&self, u: f32, v: f32, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let origin = self.lower_left_corner + u * self.horizontal + v * self.vertical;
+        Some(Ray::new(origin, self.direction, shutter_time(self.time0, self.time1, sampler)))
+    }
+}
+
+/// How a fisheye lens maps the angle from its axis to the distance from the centre of the image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FisheyeMapping {
+    /// Distance proportional to the angle, so angles can be measured straight off the image.
+    Equidistant,
+    /// Distance proportional to sin(angle / 2), so equal areas of the image cover equal solid
+    /// angles.
+    Equisolid
+}
+
+/// A fisheye camera, whose field of view fills a circle touching the top and bottom of the image.
+pub struct FisheyeCamera {
+    origin: Point3,
+    u: Vec3, v: Vec3, w: Vec3,
+    aspect_ratio: f32,
+    half_fov: f32,
+    mapping: FisheyeMapping,
+    time0: f32, time1: f32
+}
+
+impl FisheyeCamera {
+    /// A camera at `look_from` facing `look_at`, seeing `fov` degrees across its image circle
+    /// (up to 360).
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(look_from: Point3, look_at: Point3, vup: Vec3, fov: f32, aspect_ratio: f32, mapping: FisheyeMapping, time0: f32, time1: f32) -> Self {
+        let (u, v, w) = look_basis(look_from, look_at, vup);
+        Self { origin: look_from, u, v, w, aspect_ratio, half_fov: degrees_to_radians(fov.min(360.0)) / 2.0, mapping, time0, time1 }
+    }
+}
+
+impl Camera for FisheyeCamera {
+    fn get_ray(&self, u: f32, v: f32, sampler: &mut dyn Sampler) -> Option<Ray> {
+        // Coordinates on the image relative to its centre, scaled so the image circle has radius 1:
+        let (x, y) = ((2.0 * u - 1.0) * self.aspect_ratio, 2.0 * v - 1.0);
+        let r = (x * x + y * y).sqrt();
+        if r > 1.0 { return None }
+
+        let theta = match self.mapping {
+            FisheyeMapping::Equidistant => r * self.half_fov,
+            FisheyeMapping::Equisolid => 2.0 * (r * (self.half_fov / 2.0).sin()).asin()
+        };
+        let phi = y.atan2(x);
+        let direction = theta.sin() * (phi.cos() * self.u + phi.sin() * self.v) - theta.cos() * self.w;
+        Some(Ray::new(self.origin, direction, shutter_time(self.time0, self.time1, sampler)))
+    }
+}
+
+/// A 360° panorama in latitude and longitude, which should be twice as wide as it is high. The
+/// direction faced is in the centre.
+pub struct EquirectangularCamera {
+    origin: Point3,
+    u: Vec3, v: Vec3, w: Vec3,
+    time0: f32, time1: f32
+}
+
+impl EquirectangularCamera {
+    pub fn new(look_from: Point3, look_at: Point3, vup: Vec3, time0: f32, time1: f32) -> Self {
+        let (u, v, w) = look_basis(look_from, look_at, vup);
+        Self { origin: look_from, u, v, w, time0, time1 }
+    }
+}
+
+impl Camera for EquirectangularCamera {
+    fn get_ray(&self, u: f32, v: f32, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let longitude = 2.0 * PI * (u - 0.5);
+        let latitude = PI * (v - 0.5);
+        let direction = latitude.cos() * (longitude.sin() * self.u - longitude.cos() * self.w) + latitude.sin() * self.v;
+        Some(Ray::new(self.origin, direction, shutter_time(self.time0, self.time1, sampler)))
+    }
+}
+
+/// A 360° panorama as the six faces of a cube, each a 90° perspective view, laid out in two rows
+/// of three: left, front and right above; back, up and down below. The image should be 3:2.
+pub struct CubeMapCamera {
+    origin: Point3,
+    faces: [(Vec3, Vec3, Vec3); 6], // The forward, right and up directions of each face.
+    time0: f32, time1: f32
+}
+
+impl CubeMapCamera {
+    pub fn new(look_from: Point3, look_at: Point3, vup: Vec3, time0: f32, time1: f32) -> Self {
+        let (u, v, w) = look_basis(look_from, look_at, vup);
+        let faces = [
+            (-u, -w, v), (-w, u, v), (u, w, v), // Left, front, right.
+            (w, -u, v), (v, u, w), (-v, u, -w)  // Back, up, down.
+        ];
+        Self { origin: look_from, faces, time0, time1 }
+    }
+}
+
+impl Camera for CubeMapCamera {
+    fn get_ray(&self, u: f32, v: f32, sampler: &mut dyn Sampler) -> Option<Ray> {
+        // `v` runs upwards, so the top row is the upper half:
+        let column = ((u * 3.0) as usize).min(2);
+        let row = if v >= 0.5 { 0 } else { 1 };
+        let (forward, right, up) = self.faces[3 * row + column];
+        let a = 2.0 * (u * 3.0 - column as f32) - 1.0;
+        let b = 2.0 * (v * 2.0 - (1 - row) as f32) - 1.0;
+        Some(Ray::new(self.origin, forward + a * right + b * up, shutter_time(self.time0, self.time1, sampler)))
+    }
+}
+
+/// Where a camera is placed and how it sees, as chosen by a scene for showing it off.
+#[derive(Clone, Copy, Debug)]
+pub struct View {
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub vup: Vec3,
+    pub projection: Projection,
+    /// The field of view (as in `Projection::create`), or `None` for the projection's default.
+    pub fov: Option<f32>,
+    pub aperture: f32,
+    pub focus_dist: f32
+}
+
+impl View {
+    /// A pinhole perspective view from `look_from` towards `look_at`, focused on `look_at`.
+    pub fn new(look_from: Point3, look_at: Point3, vup: Vec3) -> Self {
+        Self {
+            look_from, look_at, vup, projection: Projection::Perspective, fov: None, aperture: 0.0,
+            focus_dist: (look_at - look_from).length()
+        }
+    }
+
+    pub fn with_projection(self, projection: Projection) -> Self { Self { projection, ..self } }
+    pub fn with_fov(self, fov: f32) -> Self { Self { fov: Some(fov), ..self } }
+
+    /// Gives the lens a diameter of `aperture`, focused `focus_dist` away.
+    pub fn with_aperture(self, aperture: f32, focus_dist: f32) -> Self { Self { aperture, focus_dist, ..self } }
+}
+
+/// The settings of a real camera, from which the field of view, depth of field, shutter time and
+/// exposure of a render can be worked out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhysicalCamera {
+    /// The lens's focal length, in millimetres.
+    pub focal_length: f32,
+    /// The size of the sensor (or film), in millimetres.
+    pub sensor_width: f32, pub sensor_height: f32,
+    /// The focal length divided by the diameter of the aperture.
+    pub f_number: f32,
+    /// How long the shutter is open, in seconds (of scene time).
+    pub shutter_speed: f32,
+    /// The sensor's sensitivity.
+    pub iso: f32,
+    /// Stops by which to brighten (or, if negative, darken) the image beyond what the settings
+    /// give.
+    pub exposure_compensation: f32
+}
+
+impl Default for PhysicalCamera {
+    /// A 50 mm lens at f/2.8 on a full-frame sensor, at 1/125 s and ISO 100.
+    fn default() -> Self {
+        Self {
+            focal_length: 50.0, sensor_width: 36.0, sensor_height: 24.0, f_number: 2.8, shutter_speed: 1.0 / 125.0, iso: 100.0,
+            exposure_compensation: 0.0
+        }
+    }
+}
+
+impl PhysicalCamera {
+    /// The part of the sensor used by an image of the given aspect ratio, as its width and height
+    /// in millimetres: as much of it as fits, centred.
+    pub fn sensor_size(&self, aspect_ratio: f32) -> (f32, f32) {
+        if aspect_ratio > self.sensor_width / self.sensor_height {
+            (self.sensor_width, self.sensor_width / aspect_ratio)
+        } else {
+            (self.sensor_height * aspect_ratio, self.sensor_height)
+        }
+    }
+
+    /// The vertical field of view (in degrees) of an image of the given aspect ratio, when focused
+    /// at infinity.
+    pub fn vertical_fov(&self, aspect_ratio: f32) -> f32 {
+        let (_, height) = self.sensor_size(aspect_ratio);
+        2.0 * (height / (2.0 * self.focal_length)).atan().to_degrees()
+    }
+
+    /// The diameter of the aperture, in scene units of which a millimetre is `millimetre`.
+    pub fn aperture(&self, millimetre: f32) -> f32 {
+        self.focal_length / self.f_number * millimetre
+    }
+
+    /// The exposure value at ISO 100: the base-2 logarithm of the light needed, so each step up
+    /// halves the light let in.
+    pub fn ev100(&self) -> f32 {
+        (self.f_number * self.f_number / self.shutter_speed * 100.0 / self.iso).log2()
+    }
+
+    /// The factor scaling the scene's radiance to the brightness of the image. Scenes are taken
+    /// to be lit for the default settings, which leave them as they are (so a sky of radiance 1
+    /// stays white); each stop more light the settings let in than those doubles the brightness.
+    pub fn exposure(&self) -> f32 {
+        (Self::default().ev100() - self.ev100() + self.exposure_compensation).exp2()
+    }
+}
+
+/// The distance, along the direction the camera faces, to whatever is seen at `(u, v)` on the
+/// image (as in `Camera::get_ray`) by a pinhole camera with the given field of view, so that it
+/// can be focused on. `None` if nothing is there.
+#[allow(clippy::too_many_arguments)]
+pub fn autofocus(world: &dyn Hittable, look_from: Point3, look_at: Point3, vup: Vec3, vertical_fov: f32, aspect_ratio: f32, (u, v): (f32, f32), time: f32) -> Option<f32> {
+    let camera = PerspectiveCamera::new(look_from, look_at, vup, vertical_fov, aspect_ratio, 0.0, 1.0, time, time);
+    let mut sampler = SamplerType::Independent.create(1, 0);
+    sampler.start_sample((0, 0), 0);
+    let ray = camera.get_ray(u, v, sampler.as_mut())?;
+    let hit = world.hit(&ray, 0.001, f32::INFINITY)?;
+    Some(dot(&(hit.p - look_from), &-camera.w))
+}
+
+/// The kinds of `Camera` available, which can be parsed from their names (e.g. on the command
+/// line).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Projection {
+    Perspective,
+    Orthographic,
+    Fisheye(FisheyeMapping),
+    Equirectangular,
+    CubeMap
+}
+
+impl FromStr for Projection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "perspective" => Ok(Projection::Perspective),
+            "orthographic" => Ok(Projection::Orthographic),
+            "fisheye" | "fisheye-equidistant" => Ok(Projection::Fisheye(FisheyeMapping::Equidistant)),
+            "fisheye-equisolid" => Ok(Projection::Fisheye(FisheyeMapping::Equisolid)),
+            "equirectangular" => Ok(Projection::Equirectangular),
+            "cubemap" => Ok(Projection::CubeMap),
+            _ => Err(format!("Unknown camera '{s}'; expected perspective, orthographic, fisheye, \
+                fisheye-equisolid, equirectangular or cubemap."))
+        }
+    }
+}
+
+impl Projection {
+    /// The shape of image the projection needs, if it has a fixed one.
+    pub fn aspect_ratio(&self) -> Option<f32> {
+        match self {
+            Projection::Equirectangular => Some(2.0),
+            Projection::CubeMap => Some(1.5),
+            _ => None
+        }
+    }
+
+    /// The field of view (in degrees) used when none is given.
+    pub fn default_fov(&self) -> f32 {
+        match self {
+            Projection::Fisheye(_) => 180.0,
+            _ => 70.0
+        }
+    }
+
+    /// A camera of this kind at `look_from` facing `look_at`. `fov` is the vertical field of
+    /// view of a perspective camera, or the field across a fisheye's image circle. An
+    /// orthographic camera sees the same height at `focus_dist` as a perspective camera with that
+    /// field of view would. Only the perspective camera has depth of field.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create(
+        self, look_from: Point3, look_at: Point3, vup: Vec3, fov: f32, aspect_ratio: f32, aperture: f32, focus_dist: f32, time0: f32, time1: f32
+    ) -> Box<dyn Camera> {
+        match self {
+            Projection::Perspective => Box::new(PerspectiveCamera::new(look_from, look_at, vup, fov, aspect_ratio, aperture, focus_dist, time0, time1)),
+            Projection::Orthographic => {
+                let view_height = 2.0 * focus_dist * (degrees_to_radians(fov) / 2.0).tan();
+                Box::new(OrthographicCamera::new(look_from, look_at, vup, view_height, aspect_ratio, time0, time1))
+            }
+            Projection::Fisheye(mapping) => Box::new(FisheyeCamera::new(look_from, look_at, vup, fov, aspect_ratio, mapping, time0, time1)),
+            Projection::Equirectangular => Box::new(EquirectangularCamera::new(look_from, look_at, vup, time0, time1)),
+            Projection::CubeMap => Box::new(CubeMapCamera::new(look_from, look_at, vup, time0, time1))
+        }
+    }
+}
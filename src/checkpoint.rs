@@ -0,0 +1,89 @@
+// Checkpoints of a render in progress, from which it can be resumed (or extended with more
+// samples) later.
+//
+// A checkpoint holds the accumulated float film (including any auxiliary passes), the number of
+// samples per pixel covered so far and a hash of the settings it was rendered with. Every
+// sample's random numbers are derived from the seed, pixel and sample index, so those are all the
+// random number state there is.
+
+use std::{fs, io};
+use std::io::Write;
+
+use crate::film::Film;
+use crate::render::RenderSettings;
+use crate::vec3::Colour;
+
+const MAGIC: &[u8; 4] = b"RTCK";
+const VERSION: u32 = 2;
+
+fn invalid(msg: &str) -> io::Error { io::Error::new(io::ErrorKind::InvalidData, msg.to_string()) }
+
+/// Writes `film` to `fp`. The file is written under a temporary name first, so that an
+/// interrupted write never replaces a good checkpoint with a broken one.
+pub fn save(fp: &str, film: &Film, settings: &RenderSettings) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(40 + film.width() * film.height() * 45);
+    bytes.extend(MAGIC);
+    bytes.extend(VERSION.to_le_bytes());
+    bytes.extend(settings.sample_hash().to_le_bytes());
+    for n in [film.width(), film.height(), film.next_sample()] { bytes.extend((n as u64).to_le_bytes()); }
+
+    for y in 0..film.height() {
+        for x in 0..film.width() {
+            let p = film.pixel(x, y);
+            for v in [p.sum.x(), p.sum.y(), p.sum.z(), p.sum_squares] { bytes.extend(v.to_le_bytes()); }
+            bytes.extend((p.samples as u64).to_le_bytes());
+            for v in [p.weighted_sum.x(), p.weighted_sum.y(), p.weighted_sum.z(), p.weight] { bytes.extend(v.to_le_bytes()); }
+            bytes.push(p.converged as u8);
+        }
+    }
+    for v in film.layer_data() { bytes.extend(v.to_le_bytes()); }
+
+    let temp = format!("{fp}.tmp");
+    fs::File::create(&temp)?.write_all(&bytes)?;
+    fs::rename(temp, fp)
+}
+
+/// Reads little-endian values from a byte slice, failing if it runs out.
+struct Reader<'a> { bytes: &'a [u8] }
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        if self.bytes.len() < N { return Err(invalid("Checkpoint is truncated.")) }
+        let (head, tail) = self.bytes.split_at(N);
+        self.bytes = tail;
+        Ok(head.try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> io::Result<u64> { Ok(u64::from_le_bytes(self.take()?)) }
+    fn f32(&mut self) -> io::Result<f32> { Ok(f32::from_le_bytes(self.take()?)) }
+    fn colour(&mut self) -> io::Result<Colour> { Ok(Colour::new(self.f32()?, self.f32()?, self.f32()?)) }
+}
+
+/// Reads the film saved in `fp`, failing if it was rendered with settings incompatible with
+/// `settings`.
+pub fn load(fp: &str, settings: &RenderSettings) -> io::Result<Film> {
+    let bytes = fs::read(fp)?;
+    let mut r = Reader { bytes: &bytes };
+    if &r.take::<4>()? != MAGIC { return Err(invalid("Not a checkpoint file.")) }
+    if r.take::<4>()? != VERSION.to_le_bytes() { return Err(invalid("Unsupported checkpoint version.")) }
+    if r.u64()? != settings.sample_hash() {
+        return Err(invalid("Checkpoint was rendered with different settings."))
+    }
+
+    let (width, height, next_sample) = (r.u64()? as usize, r.u64()? as usize, r.u64()? as usize);
+    let mut film = Film::with_aovs(width, height, &settings.aovs);
+    film.set_next_sample(next_sample);
+    for y in 0..height {
+        for x in 0..width {
+            let p = film.pixel_mut(x, y);
+            p.sum = r.colour()?;
+            p.sum_squares = r.f32()?;
+            p.samples = r.u64()? as usize;
+            p.weighted_sum = r.colour()?;
+            p.weight = r.f32()?;
+            p.converged = r.take::<1>()?[0] != 0;
+        }
+    }
+    for v in film.layer_data_mut() { *v = r.f32()?; }
+    Ok(film)
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::quadric::*;
+use crate::ray::Ray;
+use crate::solver::solve_quadratic;
+use crate::vec3::*;
+
+/// A closed cone, with a circular base of `radius` and its tip at `apex`.
+pub struct Cone {
+    frame: Frame,
+    height: f32,
+    radius: f32,
+    material: Arc<dyn Material>
+}
+
+impl Cone {
+    pub fn new(base: Point3, apex: Point3, radius: f32, material: Arc<dyn Material>) -> Self {
+        let axis = apex - base;
+        Self { frame: Frame::new(base, axis), height: axis.length(), radius, material }
+    }
+
+    /// Every intersection of the (local) ray with the cone's surface, in no particular order.
+    pub fn local_hits(&self, o: &Point3, d: &Vec3) -> Vec<LocalHit> {
+        let mut hits = Vec::with_capacity(2);
+
+        // Side: x^2 + y^2 = (k(h - z))^2, for 0 <= z <= h, where k = r / h.
+        let (h, k) = (self.height, self.radius / self.height);
+        let k2 = k * k;
+        let a = d.x() * d.x() + d.y() * d.y() - k2 * d.z() * d.z();
+        let b = 2.0 * (o.x() * d.x() + o.y() * d.y() + k2 * (h - o.z()) * d.z());
+        let c = o.x() * o.x() + o.y() * o.y() - k2 * (h - o.z()) * (h - o.z());
+        for t in solve_quadratic(a, b, c) {
+            let p = *o + t * *d;
+            if (0.0..=h).contains(&p.z()) {
+                // The gradient vanishes at the apex, so fall back to the axis there:
+                let gradient = Vec3::new(p.x(), p.y(), k2 * (h - p.z()));
+                let normal = if gradient.length_squared() > 1e-12 { unit_vector(&gradient) } else { Vec3::new(0.0, 0.0, 1.0) };
+                hits.push(LocalHit::new(t, normal, azimuth(&p), p.z() / h));
+            }
+        }
+
+        hits.extend(hit_cap(o, d, 0.0, self.radius, -1.0));
+        hits
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (o, d) = self.frame.ray_to_local(r);
+        closest_hit(&self.frame, &self.local_hits(&o, &d), &self.material, r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.radius;
+        Some(self.frame.bounding_box(&Aabb::new(Point3::new(-r, -r, 0.0), Point3::new(r, r, self.height))))
+    }
+}
+
+impl Solid for Cone {}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use crate::aabb::{Aabb, overlapping_box, surrounding_box};
+use crate::hittable::*;
+use crate::ray::Ray;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation { Union, Intersection, Difference }
+
+impl Operation {
+    fn inside(&self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            Operation::Union => in_left || in_right,
+            Operation::Intersection => in_left && in_right,
+            Operation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// A constructive solid geometry node, combining two solids.
+///
+/// Each surface of the result keeps the material of the solid it came from, so e.g. the walls of
+/// a hole cut with `difference` use the cutting object's material.
+pub struct Csg {
+    op: Operation,
+    left: Arc<dyn Solid>,
+    right: Arc<dyn Solid>
+}
+
+impl Csg {
+    pub fn new(op: Operation, left: Arc<dyn Solid>, right: Arc<dyn Solid>) -> Self {
+        Self { op, left, right }
+    }
+
+    pub fn union(left: Arc<dyn Solid>, right: Arc<dyn Solid>) -> Self { Self::new(Operation::Union, left, right) }
+    pub fn intersection(left: Arc<dyn Solid>, right: Arc<dyn Solid>) -> Self { Self::new(Operation::Intersection, left, right) }
+    /// The `left` solid, with `right` cut away from it.
+    pub fn difference(left: Arc<dyn Solid>, right: Arc<dyn Solid>) -> Self { Self::new(Operation::Difference, left, right) }
+}
+
+impl Solid for Csg {
+    fn intervals(&self, r: &Ray) -> Vec<Span> {
+        let left = self.left.intervals(r);
+        if left.is_empty() && self.op != Operation::Union { return vec![] }
+        let right = self.right.intervals(r);
+
+        // Every boundary of either operand, as (hit, is_left, entering), in order along the ray:
+        let mut events: Vec<_> = [(&left, true), (&right, false)].into_iter()
+            .flat_map(|(spans, is_left)| spans.iter().flat_map(move |s| [
+                (&s.enter, is_left, true),
+                (&s.exit, is_left, false),
+            ]))
+            .collect();
+        events.sort_by(|a, b| a.0.t.total_cmp(&b.0.t));
+
+        let (mut in_left, mut in_right) = (false, false);
+        let mut enter: Option<HitRecord> = None;
+        let mut spans = vec![];
+
+        for (rec, is_left, entering) in events {
+            let was_inside = self.op.inside(in_left, in_right);
+            if is_left { in_left = entering } else { in_right = entering }
+            let inside = self.op.inside(in_left, in_right);
+            if inside == was_inside { continue }
+
+            // The normal already faces the ray, but where the boundary came from a subtracted
+            // surface the sense of entering and exiting is reversed:
+            let mut rec = rec.clone();
+            rec.front_face = inside;
+            if inside {
+                enter = Some(rec);
+            } else if let Some(e) = enter.take() {
+                if rec.t > e.t { spans.push(Span { enter: e, exit: rec }); }
+            }
+        }
+        spans
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.intervals(r).into_iter()
+            .flat_map(|s| [s.enter, s.exit])
+            .find(|rec| t_min <= rec.t && rec.t <= t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (left, right) = (self.left.bounding_box(), self.right.bounding_box());
+        match self.op {
+            Operation::Union => Some(surrounding_box(&left?, &right?)),
+            Operation::Intersection => match (left, right) {
+                (Some(l), Some(r)) => overlapping_box(&l, &r).or(Some(l)),
+                (l, r) => l.or(r),
+            },
+            Operation::Difference => left,
+        }
+    }
+}
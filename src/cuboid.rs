@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use crate::aabb::{Aabb, surrounding_box};
+use crate::hittable::*;
+use crate::material::Material;
+use crate::quad::Quad;
+use crate::ray::Ray;
+use crate::transform::Transform;
+use crate::sampler::Sampler;
+use crate::vec3::*;
+
+/// A box, made up of six `Quad` faces.
+pub struct Cuboid { sides: [Quad; 6] }
+
+impl Cuboid {
+    /// An axis-aligned box with opposite corners `a` and `b`.
+    pub fn new(a: Point3, b: Point3, material: Arc<dyn Material>) -> Self {
+        let min = Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+        let max = Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+        let size = max - min;
+        Self::from_edges(
+            min,
+            Vec3::new(size.x(), 0.0, 0.0),
+            Vec3::new(0.0, size.y(), 0.0),
+            Vec3::new(0.0, 0.0, size.z()),
+            material
+        )
+    }
+
+    /// A box of the given `size`, centred on `centre` and turned by `rotation`.
+    pub fn oriented(centre: Point3, size: Vec3, rotation: &Transform, material: Arc<dyn Material>) -> Self {
+        let dx = rotation.vector(&Vec3::new(size.x(), 0.0, 0.0));
+        let dy = rotation.vector(&Vec3::new(0.0, size.y(), 0.0));
+        let dz = rotation.vector(&Vec3::new(0.0, 0.0, size.z()));
+        Self::from_edges(centre - 0.5 * (dx + dy + dz), dx, dy, dz, material)
+    }
+
+    /// The parallelepiped with one corner at `corner`, spanned by three edge vectors.
+    pub fn from_edges(corner: Point3, dx: Vec3, dy: Vec3, dz: Vec3, material: Arc<dyn Material>) -> Self {
+        let far = corner + dx + dy + dz;
+        let m = material;
+        Self { sides: [
+            // Edges are ordered so that each face's normal points outwards:
+            Quad::new(corner, dy, dx, m.clone()), // Back
+            Quad::new(corner, dz, dy, m.clone()), // Left
+            Quad::new(corner, dx, dz, m.clone()), // Bottom
+            Quad::new(far, -dx, -dy, m.clone()),  // Front
+            Quad::new(far, -dy, -dz, m.clone()),  // Right
+            Quad::new(far, -dz, -dx, m),          // Top
+        ]}
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut closest_so_far = t_max;
+        let mut hit_anything = None;
+
+        for side in &self.sides {
+            if let Some(hit) = side.hit(r, t_min, closest_so_far) {
+                closest_so_far = hit.t;
+                hit_anything = Some(hit);
+            }
+        }
+
+        hit_anything
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.sides.iter().filter_map(|s| s.bounding_box()).reduce(|a, b| surrounding_box(&a, &b))
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f32 {
+        self.sides.iter().map(|s| s.pdf_value(origin, v)).sum::<f32>() / self.sides.len() as f32
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let i = ((sampler.get_1d() * self.sides.len() as f32) as usize).min(self.sides.len() - 1);
+        self.sides[i].random(origin, sampler)
+    }
+}
+
+impl Solid for Cuboid {}
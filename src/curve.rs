@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::*;
+
+/// How a curve's cross-section is treated.
+#[derive(Clone, Copy, Debug)]
+pub enum CurveKind {
+    /// A strip which always faces the incoming ray, shaded as though it were a round tube.
+    /// Suitable for hair.
+    Cylinder,
+    /// A strip oriented by normals given at either end, e.g. for blades of grass.
+    Ribbon { n0: Vec3, n1: Vec3 }
+}
+
+/// One cubic Bézier segment of a curve, whose width varies linearly along its length.
+///
+/// Intersection follows the approach of _Physically Based Rendering_: the curve is moved into a
+/// space where the ray runs along the z-axis, then recursively split until each piece is nearly
+/// straight, and the ray's distance from that piece compared with its width.
+pub struct Curve {
+    cp: [Point3; 4],
+    width: (f32, f32),
+    kind: CurveKind,
+    max_depth: usize,
+    material: Arc<dyn Material>
+}
+
+fn lerp<T: std::ops::Add<Output = T> + std::ops::Mul<f32, Output = T>>(t: f32, a: T, b: T) -> T { a * (1.0 - t) + b * t }
+
+fn split_bezier(cp: &[Point3; 4]) -> ([Point3; 4], [Point3; 4]) {
+    let mid = |a: Point3, b: Point3| 0.5 * (a + b);
+    let (ab, bc, cd) = (mid(cp[0], cp[1]), mid(cp[1], cp[2]), mid(cp[2], cp[3]));
+    let (abc, bcd) = (mid(ab, bc), mid(bc, cd));
+    let centre = mid(abc, bcd);
+    ([cp[0], ab, abc, centre], [centre, bcd, cd, cp[3]])
+}
+
+/// Evaluates the curve and its derivative at `u`.
+fn eval_bezier(cp: &[Point3; 4], u: f32) -> (Point3, Vec3) {
+    let cp1 = [lerp(u, cp[0], cp[1]), lerp(u, cp[1], cp[2]), lerp(u, cp[2], cp[3])];
+    let cp2 = [lerp(u, cp1[0], cp1[1]), lerp(u, cp1[1], cp1[2])];
+    let deriv = if (cp2[1] - cp2[0]).length_squared() > 0.0 { 3.0 * (cp2[1] - cp2[0]) } else { cp[3] - cp[0] };
+    (lerp(u, cp2[0], cp2[1]), deriv)
+}
+
+/// Spherical linear interpolation between two unit vectors.
+fn slerp(t: f32, a: Vec3, b: Vec3) -> Vec3 {
+    let cos_theta = dot(&a, &b).clamp(-1.0, 1.0);
+    let theta = cos_theta.acos();
+    if theta.abs() < 1e-5 { return a }
+    (((1.0 - t) * theta).sin() * a + (t * theta).sin() * b) / theta.sin()
+}
+
+impl Curve {
+    pub fn new(cp: [Point3; 4], width: (f32, f32), kind: CurveKind, material: Arc<dyn Material>) -> Self {
+        let kind = match kind {
+            CurveKind::Ribbon { n0, n1 } => CurveKind::Ribbon { n0: unit_vector(&n0), n1: unit_vector(&n1) },
+            k => k
+        };
+
+        // Split until each piece deviates from a straight line by a small fraction of its width:
+        let l0 = (0..2).map(|i| {
+            let d = cp[i] - 2.0 * cp[i + 1] + cp[i + 2];
+            d.x().abs().max(d.y().abs()).max(d.z().abs())
+        }).fold(0.0, f32::max);
+        let eps = 0.05 * width.0.max(width.1);
+        let r0 = ((std::f32::consts::SQRT_2 * 6.0 * l0 / (8.0 * eps)).log2() / 2.0).max(0.0);
+        let max_depth = if r0.is_finite() { (r0.round() as usize).min(10) } else { 0 };
+
+        Self { cp, width, kind, max_depth, material }
+    }
+
+    /// Intersects the piece `cp` (in ray space) covering `u0..u1`, returning the distance and the
+    /// `(u, v)` coordinates of the closest hit between `t_min` and `t_max`.
+    #[allow(clippy::too_many_arguments)]
+    fn recursive_intersect(&self, r: &Ray, cp: &[Point3; 4], u0: f32, u1: f32, depth: usize, t_min: f32, t_max: f32) -> Option<(f32, f32, f32)> {
+        let ray_length = r.direction().length();
+        let max_width = lerp(u0, self.width.0, self.width.1).max(lerp(u1, self.width.0, self.width.1));
+
+        // Reject pieces whose (width-padded) bounds don't contain the ray, which is the z-axis:
+        let bounds = Aabb::from_points(cp);
+        let half = 0.5 * max_width;
+        if bounds.max().x() + half < 0.0 || bounds.min().x() - half > 0.0
+            || bounds.max().y() + half < 0.0 || bounds.min().y() - half > 0.0
+            || bounds.max().z() + half < ray_length * t_min || bounds.min().z() - half > ray_length * t_max {
+            return None
+        }
+
+        if depth > 0 {
+            let (lo, hi) = split_bezier(cp);
+            let um = 0.5 * (u0 + u1);
+            let first = self.recursive_intersect(r, &lo, u0, um, depth - 1, t_min, t_max);
+            let t_max = first.map_or(t_max, |h| h.0);
+            return self.recursive_intersect(r, &hi, um, u1, depth - 1, t_min, t_max).or(first)
+        }
+
+        // Test the ray against the perpendicular planes at either end of this piece:
+        let edge = (cp[1].y() - cp[0].y()) * -cp[0].y() + cp[0].x() * (cp[0].x() - cp[1].x());
+        if edge < 0.0 { return None }
+        let edge = (cp[2].y() - cp[3].y()) * -cp[3].y() + cp[3].x() * (cp[3].x() - cp[2].x());
+        if edge < 0.0 { return None }
+
+        // Find the closest point on the (nearly straight) piece to the ray:
+        let seg = Vec3::new(cp[3].x() - cp[0].x(), cp[3].y() - cp[0].y(), 0.0);
+        let denom = seg.length_squared();
+        if denom == 0.0 { return None }
+        let w = (-cp[0].x() * seg.x() - cp[0].y() * seg.y()) / denom;
+        let u = lerp(w, u0, u1).clamp(u0, u1);
+
+        let mut hit_width = lerp(u, self.width.0, self.width.1);
+        if let CurveKind::Ribbon { n0, n1 } = self.kind {
+            // A ribbon seen edge-on is narrower:
+            let n = slerp(u, n0, n1);
+            hit_width *= dot(&n, &r.direction()).abs() / ray_length;
+        }
+
+        let (pc, dpcdw) = eval_bezier(cp, w.clamp(0.0, 1.0));
+        let dist2 = pc.x() * pc.x() + pc.y() * pc.y();
+        if dist2 > hit_width * hit_width * 0.25 { return None }
+        if pc.z() < ray_length * t_min || pc.z() > ray_length * t_max { return None }
+
+        // `v` runs across the width of the curve, from 0 to 1:
+        let dist = dist2.sqrt();
+        let edge = dpcdw.x() * -pc.y() + pc.x() * dpcdw.y();
+        let v = if edge > 0.0 { 0.5 + dist / hit_width } else { 0.5 - dist / hit_width };
+        Some((pc.z() / ray_length, u, v))
+    }
+}
+
+impl Hittable for Curve {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // Move into a space where the ray starts at the origin and points along +z. Distances
+        // along z are then `t * |direction|`.
+        let ray_space = Onb::build_from_w(&r.direction());
+        let to_ray = |p: &Point3| ray_space.world_to_local(&(*p - r.origin()));
+        let cp = self.cp.map(|p| to_ray(&p));
+
+        let (t, u, v) = self.recursive_intersect(r, &cp, 0.0, 1.0, self.max_depth, t_min, t_max)?;
+
+        let (centre, dpdu) = eval_bezier(&self.cp, u);
+        let tangent = unit_vector(&dpdu);
+        let p = r.at(t);
+        let normal = match self.kind {
+            CurveKind::Ribbon { n0, n1 } => {
+                let n = slerp(u, n0, n1);
+                unit_vector(&cross(&cross(&tangent, &n), &tangent))
+            }
+            _ => {
+                // Perpendicular to the curve, facing back along the ray:
+                let d = r.direction();
+                let facing = -unit_vector(&(d - dot(&d, &tangent) * tangent));
+                match self.kind {
+                    CurveKind::Cylinder => {
+                        // Tilt the normal towards the side that was hit, as though the curve
+                        // were a round tube:
+                        let offset = p - centre;
+                        let side = offset - dot(&offset, &tangent) * tangent - dot(&offset, &facing) * facing;
+                        if side.length_squared() > 0.0 {
+                            let s = (2.0 * v - 1.0).abs().min(1.0);
+                            unit_vector(&((1.0 - s * s).sqrt() * facing + s * unit_vector(&side)))
+                        } else { facing }
+                    }
+                    _ => facing
+                }
+            }
+        };
+
+        let mut rec = HitRecord::new(p, normal, self.material.clone(), t, true);
+        rec.set_face_normal(r, &normal);
+        (rec.u, rec.v, rec.tangent) = (u, v, tangent);
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let b = Aabb::from_points(&self.cp);
+        let half = 0.5 * self.width.0.max(self.width.1);
+        let pad = Vec3::new(half, half, half);
+        Some(Aabb::new(b.min() - pad, b.max() + pad))
+    }
+}
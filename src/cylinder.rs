@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::quadric::*;
+use crate::ray::Ray;
+use crate::solver::solve_quadratic;
+use crate::vec3::*;
+
+/// A closed cylinder, running from the centre of its base to the centre of its top.
+pub struct Cylinder {
+    frame: Frame,
+    height: f32,
+    radius: f32,
+    material: Arc<dyn Material>
+}
+
+impl Cylinder {
+    pub fn new(base: Point3, top: Point3, radius: f32, material: Arc<dyn Material>) -> Self {
+        let axis = top - base;
+        Self { frame: Frame::new(base, axis), height: axis.length(), radius, material }
+    }
+
+    /// Every intersection of the (local) ray with the cylinder's surface, in no particular order.
+    pub fn local_hits(&self, o: &Point3, d: &Vec3) -> Vec<LocalHit> {
+        let mut hits = Vec::with_capacity(2);
+
+        // Side: x^2 + y^2 = r^2, for 0 <= z <= height.
+        let a = d.x() * d.x() + d.y() * d.y();
+        let b = 2.0 * (o.x() * d.x() + o.y() * d.y());
+        let c = o.x() * o.x() + o.y() * o.y() - self.radius * self.radius;
+        if a > 1e-12 {
+            for t in solve_quadratic(a, b, c) {
+                let p = *o + t * *d;
+                if (0.0..=self.height).contains(&p.z()) {
+                    let normal = Vec3::new(p.x(), p.y(), 0.0) / self.radius;
+                    hits.push(LocalHit::new(t, normal, azimuth(&p), p.z() / self.height));
+                }
+            }
+        }
+
+        hits.extend(hit_cap(o, d, 0.0, self.radius, -1.0));
+        hits.extend(hit_cap(o, d, self.height, self.radius, 1.0));
+        hits
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (o, d) = self.frame.ray_to_local(r);
+        closest_hit(&self.frame, &self.local_hits(&o, &d), &self.material, r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.radius;
+        Some(self.frame.bounding_box(&Aabb::new(Point3::new(-r, -r, 0.0), Point3::new(r, r, self.height))))
+    }
+}
+
+impl Solid for Cylinder {}
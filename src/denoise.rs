@@ -0,0 +1,122 @@
+// An edge-avoiding à-trous wavelet denoiser (after Dammertz et al., 2010, with the variance-guided
+// weights of Schied et al.'s SVGF, 2017).
+//
+// Each pass blurs the image with a 5x5 B-spline kernel whose taps are spread twice as far apart as
+// the last pass's, so a few passes cover a wide area cheaply. Each neighbour's weight falls off as
+// its normal, albedo or brightness differs from the pixel's, so edges and texture stay sharp: the
+// brightness is compared against the pixel's estimated noise, so noisy areas are smoothed more.
+
+use rayon::prelude::*;
+
+use crate::film::{luminance, Aov, Film};
+use crate::tile::Tile;
+use crate::vec3::*;
+
+/// How strongly to denoise. The defaults suit previews at a few dozen samples per pixel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Denoiser {
+    /// The number of passes, each spreading its kernel twice as wide as the last.
+    pub iterations: usize,
+    /// How many standard deviations of noise two pixels' brightness may differ by before they
+    /// stop being blurred together.
+    pub sigma_luminance: f32,
+    /// The exponent applied to the cosine between two pixels' normals; higher keeps edges sharper.
+    pub sigma_normal: f32,
+    /// How far apart two pixels' albedos may be before they stop being blurred together.
+    pub sigma_albedo: f32
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self { iterations: 5, sigma_luminance: 4.0, sigma_normal: 128.0, sigma_albedo: 0.1 }
+    }
+}
+
+const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// `a / b` for each component, leaving the component alone where `b` is near zero.
+fn safe_div(a: &Colour, b: &Colour) -> Colour {
+    let d = |a: f32, b: f32| if b > 1e-3 { a / b } else { a };
+    Colour::new(d(a.x(), b.x()), d(a.y(), b.y()), d(a.z(), b.z()))
+}
+
+/// The inverse of `safe_div`.
+fn safe_mul(a: &Colour, b: &Colour) -> Colour {
+    let m = |a: f32, b: f32| if b > 1e-3 { a * b } else { a };
+    Colour::new(m(a.x(), b.x()), m(a.y(), b.y()), m(a.z(), b.z()))
+}
+
+impl Denoiser {
+    /// The denoised colours of the pixels in `region` of `film`, row by row.
+    ///
+    /// The film should record the albedo and normal passes to guide the filter. Without them,
+    /// edges are found from the brightness alone.
+    pub fn run(&self, film: &Film, region: &Tile) -> Vec<Colour> {
+        let (width, height) = (region.width(), region.height());
+        let as_colours = |values: Vec<f32>| values.chunks_exact(3).map(|c| Vec3::new(c[0], c[1], c[2])).collect::<Vec<_>>();
+        let albedo = film.aov(Aov::Albedo, region).map(as_colours);
+        let normals = film.aov(Aov::Normal, region).map(|n| {
+            // Averaging over the pixel shortens the normals, so they're made unit length again:
+            as_colours(n).iter().map(|n| if n.length_squared() > 1e-6 { unit_vector(n) } else { *n }).collect::<Vec<_>>()
+        });
+
+        // Texture detail is in the albedo, so only the lighting (the colour divided by the
+        // albedo) is blurred, and the albedo multiplied back in at the end:
+        let colours = film.colours(region);
+        let mut lighting: Vec<_> = match &albedo {
+            Some(albedo) => colours.iter().zip(albedo).map(|(c, a)| safe_div(c, a)).collect(),
+            None => colours.clone()
+        };
+        let mut variance: Vec<_> = film.variances(region).into_iter()
+            .map(|v| if v.is_finite() { v } else { 0.0 })
+            .collect();
+
+        for iteration in 0..self.iterations {
+            let step = 1 << iteration;
+            let (l, v) = (&lighting, &variance);
+            let brightness = |i: usize| match &albedo {
+                Some(albedo) => luminance(&safe_mul(&l[i], &albedo[i])),
+                None => luminance(&l[i])
+            };
+
+            let filtered: Vec<(Colour, f32)> = (0..width * height).into_par_iter().map(|index| {
+                let (x, y) = (index % width, index / width);
+                let lum_p = brightness(index);
+                let sigma_l = self.sigma_luminance * v[index].sqrt() + 1e-4;
+
+                let (mut sum, mut sum_variance, mut total) = (Colour::default(), 0.0, 0.0);
+                for (ky, hy) in KERNEL.iter().enumerate() {
+                    for (kx, hx) in KERNEL.iter().enumerate() {
+                        let qx = x as isize + (kx as isize - 2) * step;
+                        let qy = y as isize + (ky as isize - 2) * step;
+                        if qx < 0 || qy < 0 || qx >= width as isize || qy >= height as isize { continue }
+                        let q = qy as usize * width + qx as usize;
+
+                        let mut w = hx * hy * (-(brightness(q) - lum_p).abs() / sigma_l).exp();
+                        if let Some(n) = &normals {
+                            // Pixels which saw nothing have no normal, and only match each other:
+                            let (np, nq) = (n[index], n[q]);
+                            w *= if np.length_squared() < 1e-6 && nq.length_squared() < 1e-6 { 1.0 }
+                                else { dot(&np, &nq).max(0.0).powf(self.sigma_normal) };
+                        }
+                        if let Some(a) = &albedo {
+                            w *= (-(a[index] - a[q]).length_squared() / (self.sigma_albedo * self.sigma_albedo)).exp();
+                        }
+
+                        sum += w * l[q];
+                        sum_variance += w * w * v[q];
+                        total += w;
+                    }
+                }
+                if total > 1e-8 { (sum / total, sum_variance / (total * total)) } else { (l[index], v[index]) }
+            }).collect();
+
+            (lighting, variance) = filtered.into_iter().unzip();
+        }
+
+        match &albedo {
+            Some(albedo) => lighting.iter().zip(albedo).map(|(l, a)| safe_mul(l, a)).collect(),
+            None => lighting
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::*;
+
+/// A flat, circular disk.
+pub struct Disk {
+    centre: Point3,
+    basis: Onb,
+    radius: f32,
+    material: Arc<dyn Material>
+}
+
+impl Disk {
+    pub fn new(centre: Point3, normal: Vec3, radius: f32, material: Arc<dyn Material>) -> Self {
+        Self { centre, basis: Onb::build_from_w(&normal), radius, material }
+    }
+
+    pub fn area(&self) -> f32 { PI * self.radius * self.radius }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let normal = self.basis.w();
+        let denom = dot(&normal, &r.direction());
+        if denom.abs() < 1e-8 { return None }
+
+        let t = dot(&(self.centre - r.origin()), &normal) / denom;
+        if t < t_min || t_max < t { return None }
+
+        let p = r.at(t);
+        let offset = p - self.centre;
+        if offset.length_squared() > self.radius * self.radius { return None }
+
+        let mut rec = HitRecord::new(p, normal, self.material.clone(), t, true);
+        rec.set_face_normal(r, &normal);
+
+        // Polar coordinates: `u` goes around the rim, `v` outwards from the centre.
+        let phi = dot(&offset, &self.basis.v()).atan2(dot(&offset, &self.basis.u()));
+        rec.u = (phi + PI) / (2.0 * PI);
+        rec.v = offset.length() / self.radius;
+        rec.tangent = phi.cos() * self.basis.v() - phi.sin() * self.basis.u();
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // The extent along each axis is `radius * sin(angle between the axis and the normal)`:
+        let n = self.basis.w();
+        let e = Vec3::new(
+            self.radius * (1.0 - n.x() * n.x()).max(0.0).sqrt(),
+            self.radius * (1.0 - n.y() * n.y()).max(0.0).sqrt(),
+            self.radius * (1.0 - n.z() * n.z()).max(0.0).sqrt()
+        );
+        Some(Aabb::from_points(&[self.centre - e, self.centre + e]))
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f32 {
+        let Some(rec) = self.hit(&Ray::new(*origin, *v, 0.0), 0.001, f32::INFINITY) else { return 0.0 };
+
+        let distance_squared = rec.t * rec.t * v.length_squared();
+        let cosine = (dot(v, &rec.normal) / v.length()).abs();
+        distance_squared / (cosine * self.area())
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let d = self.radius * sample_in_unit_disk(sampler.get_2d());
+        self.centre + self.basis.local(&d) - *origin
+    }
+}
@@ -0,0 +1,324 @@
+use std::str::FromStr;
+
+use crate::bitmap::Bitmap;
+use crate::filter::Filter;
+use crate::tile::Tile;
+use crate::util::col_as_rgb;
+use crate::vec3::{Colour, Point3, Vec3};
+
+/// The running totals of the samples taken for one pixel, and of the filtered samples from it
+/// and its neighbours which make up its final colour.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FilmPixel {
+    pub sum: Colour,
+    pub sum_squares: f32, // Of each sample's luminance, for estimating the variance.
+    pub samples: usize,
+    pub converged: bool,
+    pub weighted_sum: Colour,
+    pub weight: f32
+}
+
+/// The perceived brightness of a colour.
+pub fn luminance(c: &Colour) -> f32 { 0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z() }
+
+impl FilmPixel {
+    pub fn add(&mut self, colour: Colour) {
+        self.sum += colour;
+        self.sum_squares += luminance(&colour).powi(2);
+        self.samples += 1;
+    }
+
+    pub fn mean(&self) -> Colour {
+        if self.samples == 0 { Colour::default() } else { self.sum / self.samples as f32 }
+    }
+
+    /// The filtered colour, or the plain mean if no weight has been splatted here. Negative
+    /// filter lobes can ring below zero, so that is clamped away.
+    pub fn colour(&self) -> Colour {
+        let c = if self.weight > 1e-6 { self.weighted_sum / self.weight } else { self.mean() };
+        Colour::new(c.x().max(0.0), c.y().max(0.0), c.z().max(0.0))
+    }
+
+    /// The estimated variance of the mean luminance, i.e. the square of its standard error.
+    pub fn variance(&self) -> f32 {
+        if self.samples < 2 { return f32::INFINITY }
+        let n = self.samples as f32;
+        let mean = luminance(&self.sum) / n;
+        ((self.sum_squares - n * mean * mean) / (n - 1.0)).max(0.0) / n
+    }
+
+    /// The standard error of the mean luminance, relative to that mean. Dark pixels are compared
+    /// against a floor of 0.01 instead, so that they can still converge.
+    pub fn relative_error(&self) -> f32 {
+        if self.samples < 2 { return f32::INFINITY }
+        self.variance().sqrt() / (luminance(&self.sum) / self.samples as f32).max(0.01)
+    }
+}
+
+/// An auxiliary output pass, giving something other than the colour seen through each pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aov {
+    /// The distance along the camera ray to the first surface hit.
+    Depth,
+    /// The position of the first surface hit, in world space.
+    Position,
+    /// The shading normal of the first surface hit, facing the camera.
+    Normal,
+    /// The colour of the first surface hit, ignoring lighting (or of the sky, where nothing is).
+    Albedo,
+    /// Which of the world's objects the pixel's first sample hit, counting from 1.
+    ObjectId,
+    /// Which material the pixel's first sample hit, numbered from 1 in the order they're seen.
+    MaterialId,
+    /// The surface coordinates of the first surface hit.
+    Uv,
+    /// The number of samples taken.
+    Samples
+}
+
+impl Aov {
+    pub const ALL: [Aov; 8] = [
+        Aov::Depth, Aov::Position, Aov::Normal, Aov::Albedo, Aov::ObjectId, Aov::MaterialId, Aov::Uv, Aov::Samples
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+            Aov::Uv => "uv",
+            Aov::Samples => "samples"
+        }
+    }
+
+    /// The number of values the pass has for each pixel.
+    pub fn channels(&self) -> usize {
+        match self {
+            Aov::Depth | Aov::ObjectId | Aov::MaterialId | Aov::Samples => 1,
+            Aov::Uv => 2,
+            Aov::Position | Aov::Normal | Aov::Albedo => 3
+        }
+    }
+
+    /// Whether the pass holds the first sample's value, rather than the average of every sample.
+    fn is_id(&self) -> bool { matches!(self, Aov::ObjectId | Aov::MaterialId) }
+}
+
+impl FromStr for Aov {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Aov::ALL.into_iter().find(|aov| aov.name() == s).ok_or_else(|| format!(
+            "Unknown pass '{s}'; expected {}.", Aov::ALL.map(|aov| aov.name()).join(", ")
+        ))
+    }
+}
+
+/// What one sample's camera ray hit, for the auxiliary passes. Rays which hit nothing give zero
+/// for everything but the albedo.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AovSample {
+    pub depth: f32,
+    pub position: Point3,
+    pub normal: Vec3,
+    pub albedo: Colour,
+    pub object_id: usize,
+    pub material_key: usize, // See `HitRecord::material_key`, or zero for no material.
+    pub uv: (f32, f32)
+}
+
+/// The totals for one auxiliary pass, with `aov.channels()` values for each pixel.
+#[derive(Clone, Debug)]
+struct Layer {
+    aov: Aov,
+    data: Vec<f32>
+}
+
+/// The accumulated samples of an image, stored row by row from the top, along with any
+/// auxiliary passes.
+#[derive(Clone, Debug)]
+pub struct Film {
+    width: usize,
+    height: usize,
+    pixels: Vec<FilmPixel>,
+    next_sample: usize,
+    layers: Vec<Layer>,
+    material_keys: Vec<usize> // In order of their IDs, from 1.
+}
+
+impl Film {
+    /// A film which also records the auxiliary passes `aovs`.
+    pub fn with_aovs(width: usize, height: usize, aovs: &[Aov]) -> Self {
+        // The sample counts are already kept for every pixel, so don't need a layer:
+        let layers = aovs.iter().filter(|&&aov| aov != Aov::Samples)
+            .map(|&aov| Layer { aov, data: vec![0.0; width * height * aov.channels()] })
+            .collect();
+        Self {
+            width, height, pixels: vec![FilmPixel::default(); width * height], next_sample: 0,
+            layers, material_keys: vec![]
+        }
+    }
+
+    pub fn width(&self) -> usize { self.width }
+    pub fn height(&self) -> usize { self.height }
+
+    pub fn pixel(&self, x: usize, y: usize) -> &FilmPixel { &self.pixels[y * self.width + x] }
+    pub fn pixel_mut(&mut self, x: usize, y: usize) -> &mut FilmPixel { &mut self.pixels[y * self.width + x] }
+
+    /// The index of the next sample to take for each pixel still being sampled, i.e. the number
+    /// of samples per pixel covered by the passes so far.
+    pub fn next_sample(&self) -> usize { self.next_sample }
+    pub fn set_next_sample(&mut self, next_sample: usize) { self.next_sample = next_sample }
+
+    /// The pixels in `region`, row by row.
+    fn region_pixels<'a>(&'a self, region: &Tile) -> impl Iterator<Item = &'a FilmPixel> + 'a {
+        let region = *region;
+        (region.y0..region.y1).flat_map(move |y| (region.x0..region.x1).map(move |x| self.pixel(x, y)))
+    }
+
+    /// The filtered colour of each pixel in `region`, row by row.
+    pub fn colours(&self, region: &Tile) -> Vec<Colour> { self.region_pixels(region).map(|p| p.colour()).collect() }
+
+    /// The variance of each pixel's luminance in `region`, row by row.
+    pub fn variances(&self, region: &Tile) -> Vec<f32> { self.region_pixels(region).map(|p| p.variance()).collect() }
+
+    /// The average number of samples taken by each pixel in `region`.
+    pub fn mean_samples(&self, region: &Tile) -> f32 {
+        self.region_pixels(region).map(|p| p.samples).sum::<usize>() as f32 / region.area().max(1) as f32
+    }
+
+    /// Adds a sample taken at `(x, y)`, measured in pixels from the top-left corner of the image,
+    /// to every pixel whose centre lies within the filter's radius.
+    pub fn splat(&mut self, (x, y): (f32, f32), colour: Colour, filter: &Filter) {
+        let r = filter.radius();
+        let x0 = ((x - 0.5 - r).floor() + 1.0).max(0.0) as usize;
+        let y0 = ((y - 0.5 - r).floor() + 1.0).max(0.0) as usize;
+        let x1 = ((x - 0.5 + r).floor() as isize).min(self.width as isize - 1);
+        let y1 = ((y - 0.5 + r).floor() as isize).min(self.height as isize - 1);
+        for py in y0 as isize..=y1 {
+            for px in x0 as isize..=x1 {
+                let (px, py) = (px as usize, py as usize);
+                let w = filter.evaluate(x - (px as f32 + 0.5), y - (py as f32 + 0.5));
+                let pixel = self.pixel_mut(px, py);
+                pixel.weighted_sum += w * colour;
+                pixel.weight += w;
+            }
+        }
+    }
+
+    /// The ID of the material with `key`, giving it the next one if it hasn't been seen before.
+    fn material_id(&mut self, key: usize) -> usize {
+        if key == 0 { return 0 }
+        match self.material_keys.iter().position(|&k| k == key) {
+            Some(i) => i + 1,
+            None => {
+                self.material_keys.push(key);
+                self.material_keys.len()
+            }
+        }
+    }
+
+    /// Adds one sample to the auxiliary passes of the pixel at `(x, y)`. The IDs are only taken
+    /// from the `first` sample of each pixel.
+    pub fn add_aovs(&mut self, x: usize, y: usize, sample: &AovSample, first: bool) {
+        let material_id = if first { self.material_id(sample.material_key) } else { 0 };
+        let index = y * self.width + x;
+        for layer in &mut self.layers {
+            let n = layer.aov.channels();
+            let values = &mut layer.data[index * n..(index + 1) * n];
+            let v = |c: Vec3| [c.x(), c.y(), c.z()];
+            match layer.aov {
+                Aov::Depth => values[0] += sample.depth,
+                Aov::Position => values.iter_mut().zip(v(sample.position)).for_each(|(a, b)| *a += b),
+                Aov::Normal => values.iter_mut().zip(v(sample.normal)).for_each(|(a, b)| *a += b),
+                Aov::Albedo => values.iter_mut().zip(v(sample.albedo)).for_each(|(a, b)| *a += b),
+                Aov::Uv => values.iter_mut().zip([sample.uv.0, sample.uv.1]).for_each(|(a, b)| *a += b),
+                Aov::ObjectId => if first { values[0] = sample.object_id as f32 },
+                Aov::MaterialId => if first { values[0] = material_id as f32 },
+                Aov::Samples => {}
+            }
+        }
+    }
+
+    /// The values of `aov` for each pixel in `region`, row by row, with `aov.channels()` for each
+    /// pixel. Returns `None` if the pass wasn't recorded.
+    pub fn aov(&self, aov: Aov, region: &Tile) -> Option<Vec<f32>> {
+        if aov == Aov::Samples {
+            return Some(self.region_pixels(region).map(|p| p.samples as f32).collect())
+        }
+
+        let layer = self.layers.iter().find(|l| l.aov == aov)?;
+        let n = aov.channels();
+        let mut values = Vec::with_capacity(region.area() * n);
+        for y in region.y0..region.y1 {
+            for x in region.x0..region.x1 {
+                let index = y * self.width + x;
+                let scale = if aov.is_id() { 1.0 } else { 1.0 / self.pixels[index].samples.max(1) as f32 };
+                values.extend(layer.data[index * n..(index + 1) * n].iter().map(|v| v * scale));
+            }
+        }
+        Some(values)
+    }
+
+    /// The raw totals of every auxiliary pass, one after another, for saving.
+    pub fn layer_data(&self) -> impl Iterator<Item = &f32> { self.layers.iter().flat_map(|l| &l.data) }
+    pub fn layer_data_mut(&mut self) -> impl Iterator<Item = &mut f32> { self.layers.iter_mut().flat_map(|l| &mut l.data) }
+
+    /// Adds the totals from `tile`, a smaller film whose top-left pixel lies at `offset` in this
+    /// one. Any part of it outside this film is ignored. The tile must record the same passes.
+    pub fn merge(&mut self, tile: &Film, (ox, oy): (isize, isize)) {
+        // The tile numbers materials in the order it saw them, so they're renumbered in ours:
+        let material_ids: Vec<_> = tile.material_keys.iter().map(|&key| self.material_id(key)).collect();
+
+        for ty in 0..tile.height {
+            for tx in 0..tile.width {
+                let (x, y) = (ox + tx as isize, oy + ty as isize);
+                if x < 0 || y < 0 || x >= self.width as isize || y >= self.height as isize { continue }
+
+                let src = tile.pixel(tx, ty);
+                let dst = self.pixel_mut(x as usize, y as usize);
+                dst.sum += src.sum;
+                dst.sum_squares += src.sum_squares;
+                dst.samples += src.samples;
+                dst.weighted_sum += src.weighted_sum;
+                dst.weight += src.weight;
+
+                let (src, dst) = (ty * tile.width + tx, y as usize * self.width + x as usize);
+                for (layer, tile_layer) in self.layers.iter_mut().zip(&tile.layers) {
+                    let n = layer.aov.channels();
+                    let (to, from) = (&mut layer.data[dst * n..(dst + 1) * n], &tile_layer.data[src * n..(src + 1) * n]);
+                    match layer.aov {
+                        Aov::ObjectId => if from[0] != 0.0 { to[0] = from[0] },
+                        Aov::MaterialId => if from[0] != 0.0 { to[0] = material_ids[from[0] as usize - 1] as f32 },
+                        _ => to.iter_mut().zip(from).for_each(|(a, b)| *a += b)
+                    }
+                }
+            }
+        }
+    }
+
+    /// The number of pixels which haven't converged yet.
+    pub fn total_active(&self) -> usize { self.pixels.iter().filter(|p| !p.converged).count() }
+
+    /// Converts the filtered colour of each pixel in `region`, scaled by `exposure`, to an 8-bit,
+    /// gamma-corrected image.
+    pub fn to_bitmap(&self, region: &Tile, exposure: f32) -> Bitmap {
+        Bitmap::new(self.region_pixels(region).map(|p| col_as_rgb(&(exposure * p.colour()), 1)).collect(), region.width())
+    }
+
+    /// An image of how many samples each pixel in `region` took, from black (the fewest) through
+    /// red and yellow to white (the most).
+    pub fn sample_heatmap(&self, region: &Tile) -> Bitmap {
+        let (min, max) = self.region_pixels(region)
+            .fold((usize::MAX, 0), |(lo, hi), p| (lo.min(p.samples), hi.max(p.samples)));
+        let range = (max - min).max(1) as f32;
+        let data = self.region_pixels(region).map(|p| {
+            let t = (p.samples - min) as f32 / range;
+            [3.0 * t, 3.0 * t - 1.0, 3.0 * t - 2.0].map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8)
+        }).collect();
+        Bitmap::new(data, region.width())
+    }
+}
@@ -0,0 +1,93 @@
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+/// A pixel reconstruction filter, giving the weight of a sample at an offset (in pixels) from a
+/// pixel's centre. Each is separable, and zero beyond `radius` along either axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    /// Every sample within the radius weighted equally. A radius of 0.5 averages each pixel's
+    /// own samples, as used originally.
+    Box { radius: f32 },
+    /// Weights falling linearly to zero at the radius.
+    Tent { radius: f32 },
+    /// A Gaussian with standard deviation `sigma`, shifted down to reach zero at the radius.
+    Gaussian { radius: f32, sigma: f32 },
+    /// The Mitchell-Netravali cubic; `b = c = 1/3` is their recommended compromise between
+    /// blurring and ringing.
+    Mitchell { radius: f32, b: f32, c: f32 },
+    /// A sinc windowed by a wider sinc, with as many lobes as the radius.
+    Lanczos { radius: f32 }
+}
+
+/// sin(πx) / πx.
+fn sinc(x: f32) -> f32 {
+    let x = PI * x;
+    if x.abs() < 1e-5 { 1.0 } else { x.sin() / x }
+}
+
+/// The Mitchell-Netravali polynomial, for `x` in [0, 2].
+fn mitchell(x: f32, b: f32, c: f32) -> f32 {
+    if x > 1.0 {
+        ((-b - 6.0 * c) * x.powi(3) + (6.0 * b + 30.0 * c) * x.powi(2)
+            + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+    } else {
+        ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3) + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+            + (6.0 - 2.0 * b)) / 6.0
+    }
+}
+
+/// Parses a filter's name, optionally followed by its radius (e.g. `mitchell` or `gaussian:2`).
+/// Filters left at their default radius cover the range usually used for them.
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, radius) = match s.split_once(':') {
+            Some((name, radius)) => (name, Some(radius.parse::<f32>().map_err(|_| format!("Filter radius '{radius}' isn't a number."))?)),
+            None => (s, None)
+        };
+        match name {
+            "box" => Ok(Filter::Box { radius: radius.unwrap_or(0.5) }),
+            "tent" => Ok(Filter::Tent { radius: radius.unwrap_or(1.0) }),
+            "gaussian" => {
+                let radius = radius.unwrap_or(1.5);
+                Ok(Filter::Gaussian { radius, sigma: radius / 3.0 })
+            }
+            "mitchell" => Ok(Filter::Mitchell { radius: radius.unwrap_or(2.0), b: 1.0 / 3.0, c: 1.0 / 3.0 }),
+            "lanczos" => Ok(Filter::Lanczos { radius: radius.unwrap_or(3.0) }),
+            _ => Err(format!("Unknown filter '{name}'; expected box, tent, gaussian, mitchell or lanczos."))
+        }
+    }
+}
+
+impl Filter {
+    pub fn radius(&self) -> f32 {
+        match *self {
+            Filter::Box { radius } | Filter::Tent { radius } | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. } | Filter::Lanczos { radius } => radius
+        }
+    }
+
+    /// The weight along one axis, at a distance `x` from the centre.
+    fn evaluate_1d(&self, x: f32) -> f32 {
+        let x = x.abs();
+        let radius = self.radius();
+        if x > radius { return 0.0 }
+        match *self {
+            Filter::Box { .. } => 1.0,
+            Filter::Tent { .. } => radius - x,
+            Filter::Gaussian { sigma, .. } => {
+                let gaussian = |x: f32| (-x * x / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(radius)).max(0.0)
+            }
+            Filter::Mitchell { b, c, .. } => mitchell(2.0 * x / radius, b, c),
+            Filter::Lanczos { .. } => sinc(x) * sinc(x / radius)
+        }
+    }
+
+    /// The weight of a sample at `(dx, dy)` from a pixel's centre. This may be negative for the
+    /// Mitchell and Lanczos filters.
+    pub fn evaluate(&self, dx: f32, dy: f32) -> f32 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+}
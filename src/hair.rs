@@ -0,0 +1,256 @@
+use std::f32::consts::PI;
+
+use crate::hittable::HitRecord;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::util::degrees_to_radians;
+use crate::vec3::*;
+
+/// The number of scattering lobes evaluated individually; any further bounces inside the fibre
+/// are lumped together into one last lobe.
+const P_MAX: usize = 3;
+
+/// A hair (or other fibre) scattering model after d'Eon et al. and Chiang et al., as presented in
+/// _Physically Based Rendering_.
+///
+/// Light scatters in lobes according to the number of times it passes through the fibre: `R`
+/// (reflected off the surface), `TT` (straight through), `TRT` (reflected once inside), and the
+/// rest. Each lobe has a longitudinal part, depending on the angle to the fibre, and an azimuthal
+/// part, depending on where around the fibre the ray struck. This needs the fibre's direction in
+/// `HitRecord::tangent`, and its offset across the fibre in `v`, as given by `Curve`.
+pub struct HairBsdf {
+    eta: f32,
+    sigma_a: Colour,
+    v: [f32; P_MAX + 1], // Longitudinal variance of each lobe.
+    s: f32, // Azimuthal logistic scale.
+    sin_2k_alpha: [f32; 3],
+    cos_2k_alpha: [f32; 3],
+    albedo: Colour // The reflectance which `from_reflectance` would take to give `sigma_a`.
+}
+
+impl HairBsdf {
+    /// `sigma_a` is the absorption coefficient of the fibre's interior, `beta_m` and `beta_n` the
+    /// longitudinal and azimuthal roughness (from 0 to 1), and `alpha` the tilt of the cuticle
+    /// scales in degrees (typically around 2).
+    pub fn new(sigma_a: Colour, beta_m: f32, beta_n: f32, alpha: f32) -> Self {
+        let v0 = (0.726 * beta_m + 0.812 * beta_m * beta_m + 3.7 * beta_m.powi(20)).powi(2);
+        let v = [v0, 0.25 * v0, 4.0 * v0, 4.0 * v0];
+        let s = (PI / 8.0).sqrt() * (0.265 * beta_n + 1.194 * beta_n * beta_n + 5.372 * beta_n.powi(22));
+
+        let mut sin_2k_alpha = [degrees_to_radians(alpha).sin(), 0.0, 0.0];
+        let mut cos_2k_alpha = [(1.0 - sin_2k_alpha[0] * sin_2k_alpha[0]).max(0.0).sqrt(), 0.0, 0.0];
+        for i in 1..3 {
+            sin_2k_alpha[i] = 2.0 * cos_2k_alpha[i - 1] * sin_2k_alpha[i - 1];
+            cos_2k_alpha[i] = cos_2k_alpha[i - 1].powi(2) - sin_2k_alpha[i - 1].powi(2);
+        }
+
+        let denom = reflectance_denominator(beta_n);
+        let albedo = Colour::new(
+            (-sigma_a.x().sqrt() * denom).exp(), (-sigma_a.y().sqrt() * denom).exp(), (-sigma_a.z().sqrt() * denom).exp()
+        );
+        Self { eta: 1.55, sigma_a, v, s, sin_2k_alpha, cos_2k_alpha, albedo }
+    }
+
+    /// Hair coloured by its concentrations of eumelanin (brown/black, from 0 up to about 8) and
+    /// pheomelanin (red/yellow).
+    pub fn from_melanin(eumelanin: f32, pheomelanin: f32, beta_m: f32, beta_n: f32) -> Self {
+        let sigma_a = eumelanin * Colour::new(0.419, 0.697, 1.37) + pheomelanin * Colour::new(0.187, 0.4, 1.05);
+        Self::new(sigma_a, beta_m, beta_n, 2.0)
+    }
+
+    /// Tilts the outgoing direction's longitudinal angle to account for the cuticle scales.
+    fn tilt(&self, p: usize, sin_theta_o: f32, cos_theta_o: f32) -> (f32, f32) {
+        let (sin_theta, cos_theta) = match p {
+            0 => (sin_theta_o * self.cos_2k_alpha[1] - cos_theta_o * self.sin_2k_alpha[1],
+                  cos_theta_o * self.cos_2k_alpha[1] + sin_theta_o * self.sin_2k_alpha[1]),
+            1 => (sin_theta_o * self.cos_2k_alpha[0] + cos_theta_o * self.sin_2k_alpha[0],
+                  cos_theta_o * self.cos_2k_alpha[0] - sin_theta_o * self.sin_2k_alpha[0]),
+            2 => (sin_theta_o * self.cos_2k_alpha[2] + cos_theta_o * self.sin_2k_alpha[2],
+                  cos_theta_o * self.cos_2k_alpha[2] - sin_theta_o * self.sin_2k_alpha[2]),
+            _ => (sin_theta_o, cos_theta_o)
+        };
+        (sin_theta, cos_theta.abs())
+    }
+
+    /// The fraction of light carried by each lobe, along with the angles of the refracted ray.
+    fn attenuation(&self, sin_theta_o: f32, cos_theta_o: f32, h: f32) -> ([Colour; P_MAX + 1], f32) {
+        let sin_theta_t = sin_theta_o / self.eta;
+        let cos_theta_t = safe_sqrt(1.0 - sin_theta_t * sin_theta_t);
+        let etap = safe_sqrt(self.eta * self.eta - sin_theta_o * sin_theta_o) / cos_theta_o;
+        let sin_gamma_t = h / etap;
+        let cos_gamma_t = safe_sqrt(1.0 - sin_gamma_t * sin_gamma_t);
+        let gamma_t = sin_gamma_t.clamp(-1.0, 1.0).asin();
+
+        // Transmittance across the fibre, once:
+        let d = 2.0 * cos_gamma_t / cos_theta_t;
+        let t = Colour::new((-self.sigma_a.x() * d).exp(), (-self.sigma_a.y() * d).exp(), (-self.sigma_a.z() * d).exp());
+
+        let cos_gamma_o = safe_sqrt(1.0 - h * h);
+        let f = fresnel(cos_theta_o * cos_gamma_o, self.eta);
+        let white = Colour::new(1.0, 1.0, 1.0);
+        let mut ap = [Colour::default(); P_MAX + 1];
+        ap[0] = f * white;
+        ap[1] = (1.0 - f) * (1.0 - f) * t;
+        ap[2] = f * ap[1] * t;
+        let tf = f * t;
+        ap[3] = ap[2] * tf * Colour::new(1.0 / (1.0 - tf.x()), 1.0 / (1.0 - tf.y()), 1.0 / (1.0 - tf.z()));
+        (ap, gamma_t)
+    }
+
+    /// The probability of sampling each lobe, in proportion to its (average) attenuation.
+    fn lobe_pdf(ap: &[Colour; P_MAX + 1]) -> [f32; P_MAX + 1] {
+        let weight = ap.map(|a| (a.x() + a.y() + a.z()) / 3.0);
+        let sum: f32 = weight.iter().sum();
+        if sum > 0.0 { weight.map(|w| w / sum) } else { [1.0 / (P_MAX + 1) as f32; P_MAX + 1] }
+    }
+
+    /// Evaluates the BSDF (multiplied by the cosine term) and its sampling pdf for a pair of
+    /// directions in the fibre's local frame, where x is along the fibre.
+    fn evaluate(&self, wo: &Vec3, wi: &Vec3, h: f32) -> (Colour, f32) {
+        let (sin_theta_o, sin_theta_i) = (wo.x().clamp(-1.0, 1.0), wi.x().clamp(-1.0, 1.0));
+        let (cos_theta_o, cos_theta_i) = (safe_sqrt(1.0 - sin_theta_o * sin_theta_o), safe_sqrt(1.0 - sin_theta_i * sin_theta_i));
+        let phi = wi.z().atan2(wi.y()) - wo.z().atan2(wo.y());
+        let gamma_o = h.clamp(-1.0, 1.0).asin();
+
+        let (ap, gamma_t) = self.attenuation(sin_theta_o, cos_theta_o, h);
+        let lobe_pdf = Self::lobe_pdf(&ap);
+
+        let (mut f, mut pdf) = (Colour::default(), 0.0);
+        for p in 0..=P_MAX {
+            let (sin_theta_op, cos_theta_op) = self.tilt(p, sin_theta_o, cos_theta_o);
+            let mp = longitudinal(cos_theta_i, cos_theta_op, sin_theta_i, sin_theta_op, self.v[p]);
+            let np = if p < P_MAX { azimuthal(phi, p, self.s, gamma_o, gamma_t) } else { 1.0 / (2.0 * PI) };
+            f += mp * np * ap[p];
+            pdf += mp * np * lobe_pdf[p];
+        }
+        (f, pdf)
+    }
+}
+
+fn safe_sqrt(x: f32) -> f32 { x.max(0.0).sqrt() }
+
+/// Fresnel reflectance for unpolarised light entering a dielectric from air.
+fn fresnel(cos_theta_i: f32, eta: f32) -> f32 {
+    let cos_i = cos_theta_i.clamp(-1.0, 1.0);
+    let sin_t = safe_sqrt(1.0 - cos_i * cos_i) / eta;
+    if sin_t >= 1.0 { return 1.0 }
+    let cos_t = safe_sqrt(1.0 - sin_t * sin_t);
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (parallel * parallel + perpendicular * perpendicular)
+}
+
+/// The modified Bessel function of the first kind, order zero.
+fn i0(x: f32) -> f32 {
+    let (mut val, mut x2i, mut ifact, mut i4) = (0.0, 1.0, 1.0, 1.0);
+    for i in 0..10 {
+        if i > 1 { ifact *= i as f32; }
+        val += x2i / (i4 * ifact * ifact);
+        x2i *= x * x;
+        i4 *= 4.0;
+    }
+    val
+}
+
+fn log_i0(x: f32) -> f32 {
+    if x > 12.0 {
+        x + 0.5 * (-(2.0 * PI).ln() + (1.0 / x).ln() + 1.0 / (8.0 * x))
+    } else { i0(x).ln() }
+}
+
+/// The longitudinal scattering function, `M_p`.
+fn longitudinal(cos_theta_i: f32, cos_theta_o: f32, sin_theta_i: f32, sin_theta_o: f32, v: f32) -> f32 {
+    let a = cos_theta_i * cos_theta_o / v;
+    let b = sin_theta_i * sin_theta_o / v;
+    // Low variances would overflow, so work with logarithms there:
+    if v <= 0.1 {
+        (log_i0(a) - b - 1.0 / v + std::f32::consts::LN_2 + (1.0 / (2.0 * v)).ln()).exp()
+    } else {
+        ((-b).exp() * i0(a)) / ((1.0 / v).sinh() * 2.0 * v)
+    }
+}
+
+/// The azimuthal angle by which lobe `p` leaves the fibre, relative to where it entered.
+fn lobe_phi(p: usize, gamma_o: f32, gamma_t: f32) -> f32 {
+    2.0 * p as f32 * gamma_t - 2.0 * gamma_o + p as f32 * PI
+}
+
+fn logistic(x: f32, s: f32) -> f32 {
+    let e = (-x.abs() / s).exp();
+    e / (s * (1.0 + e) * (1.0 + e))
+}
+
+fn logistic_cdf(x: f32, s: f32) -> f32 { 1.0 / (1.0 + (-x / s).exp()) }
+
+/// The azimuthal scattering function, `N_p`: a logistic distribution trimmed to `-PI..PI`.
+fn azimuthal(phi: f32, p: usize, s: f32, gamma_o: f32, gamma_t: f32) -> f32 {
+    let mut dphi = phi - lobe_phi(p, gamma_o, gamma_t);
+    while dphi > PI { dphi -= 2.0 * PI; }
+    while dphi < -PI { dphi += 2.0 * PI; }
+    logistic(dphi, s) / (logistic_cdf(PI, s) - logistic_cdf(-PI, s))
+}
+
+/// Part of the fit (by Chiang et al.) relating the absorption coefficient to the colour of the
+/// hair after multiple scattering, for azimuthal roughness `b`.
+fn reflectance_denominator(b: f32) -> f32 {
+    5.969 - 0.215 * b + 2.532 * b.powi(2) - 10.73 * b.powi(3) + 5.574 * b.powi(4) + 0.245 * b.powi(5)
+}
+
+fn sample_trimmed_logistic(u: f32, s: f32) -> f32 {
+    let k = logistic_cdf(PI, s) - logistic_cdf(-PI, s);
+    let x = -s * (1.0 / (u * k + logistic_cdf(-PI, s)) - 1.0).ln();
+    x.clamp(-PI, PI)
+}
+
+impl Material for HairBsdf {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<(Ray, Colour)> {
+        // Build a frame with x along the fibre and z along the normal:
+        let tangent = rec.tangent - dot(&rec.tangent, &rec.normal) * rec.normal;
+        let x = if tangent.length_squared() > 1e-12 { unit_vector(&tangent) } else { Onb::build_from_w(&rec.normal).u() };
+        let y = cross(&rec.normal, &x);
+        let to_local = |a: &Vec3| Vec3::new(dot(a, &x), dot(a, &y), dot(a, &rec.normal));
+
+        let wo = to_local(&-unit_vector(&r_in.direction()));
+        let h = -1.0 + 2.0 * rec.v;
+        let (sin_theta_o, cos_theta_o) = (wo.x().clamp(-1.0, 1.0), safe_sqrt(1.0 - wo.x() * wo.x()));
+        let gamma_o = h.clamp(-1.0, 1.0).asin();
+
+        // Choose a lobe:
+        let (ap, gamma_t) = self.attenuation(sin_theta_o, cos_theta_o, h);
+        let lobe_pdf = Self::lobe_pdf(&ap);
+        let mut u = sampler.get_1d();
+        let mut p = 0;
+        while p < P_MAX && u >= lobe_pdf[p] {
+            u -= lobe_pdf[p];
+            p += 1;
+        }
+
+        // Sample its longitudinal and azimuthal distributions:
+        let (sin_theta_op, cos_theta_op) = self.tilt(p, sin_theta_o, cos_theta_o);
+        let v = self.v[p];
+        let (u1, u2) = sampler.get_2d();
+        let u1 = u1.max(1e-5);
+        let cos_theta = 1.0 + v * (u1 + (1.0 - u1) * (-2.0 / v).exp()).ln();
+        let sin_theta = safe_sqrt(1.0 - cos_theta * cos_theta);
+        let cos_phi = (2.0 * PI * u2).cos();
+        let sin_theta_i = -cos_theta * sin_theta_op + sin_theta * cos_phi * cos_theta_op;
+        let cos_theta_i = safe_sqrt(1.0 - sin_theta_i * sin_theta_i);
+
+        let u3 = sampler.get_1d();
+        let dphi = if p < P_MAX {
+            lobe_phi(p, gamma_o, gamma_t) + sample_trimmed_logistic(u3, self.s)
+        } else { 2.0 * PI * u3 };
+        let phi_i = wo.z().atan2(wo.y()) + dphi;
+        let wi = Vec3::new(sin_theta_i, cos_theta_i * phi_i.cos(), cos_theta_i * phi_i.sin());
+
+        let (f, pdf) = self.evaluate(&wo, &wi, h);
+        if pdf <= 0.0 || !pdf.is_finite() { return None }
+
+        let direction = wi.x() * x + wi.y() * y + wi.z() * rec.normal;
+        Some((Ray::new(rec.p, direction, r_in.time()), f / pdf))
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Colour { self.albedo }
+}
@@ -0,0 +1,216 @@
+use std::{fs, io};
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::bitmap::{self, Bitmap};
+use crate::hittable::*;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::*;
+
+/// A rectangular grid of heights, stored row by row.
+#[derive(Clone, Debug)]
+pub struct HeightMap {
+    width: usize,
+    depth: usize,
+    heights: Vec<f32>
+}
+
+impl HeightMap {
+    pub fn new(width: usize, depth: usize, heights: Vec<f32>) -> Self {
+        assert!(width >= 2 && depth >= 2, "A height map needs at least 2x2 samples.");
+        assert_eq!(heights.len(), width * depth);
+        Self { width, depth, heights }
+    }
+
+    pub fn width(&self) -> usize { self.width }
+    pub fn depth(&self) -> usize { self.depth }
+    pub fn at(&self, x: usize, z: usize) -> f32 { self.heights[z * self.width + x] }
+
+    /// Loads a grayscale (or colour, which is averaged) BMP or PNG image, with heights from 0
+    /// for black up to 1 for white.
+    pub fn load_image(fp: &str) -> io::Result<Self> {
+        if fp.to_lowercase().ends_with(".png") {
+            Self::load_png(fp)
+        } else {
+            Ok(Self::from_bitmap(&Bitmap::read(fs::File::open(fp)?)?))
+        }
+    }
+
+    pub fn from_bitmap(bmp: &Bitmap) -> Self {
+        let heights = (0..bmp.height())
+            .flat_map(|z| (0..bmp.width()).map(move |x| (x, z)))
+            .map(|(x, z)| bmp.pixel(x, z).iter().map(|&c| c as f32).sum::<f32>() / (3.0 * 255.0))
+            .collect();
+        Self::new(bmp.width(), bmp.height(), heights)
+    }
+
+    /// Loads a PNG, keeping the full precision of 16-bit images.
+    pub fn load_png(fp: &str) -> io::Result<Self> {
+        let (width, depth, pixels) = bitmap::read_png(fp)?;
+        let heights = pixels.iter().map(|c| (c.x() + c.y() + c.z()) / 3.0).collect();
+        Ok(Self::new(width, depth, heights))
+    }
+
+    /// Loads a headerless file of `width * depth` little-endian `f32`s.
+    pub fn load_raw(fp: &str, width: usize, depth: usize) -> io::Result<Self> {
+        let bytes = fs::read(fp)?;
+        if bytes.len() != width * depth * 4 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Raw height map has the wrong size."))
+        }
+        let heights = bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+        Ok(Self::new(width, depth, heights))
+    }
+}
+
+/// The lowest and highest heights within each block of cells at one level of the hierarchy.
+struct MipLevel {
+    width: usize,
+    depth: usize,
+    ranges: Vec<(f32, f32)>
+}
+
+/// Terrain built from a `HeightMap`, spanning `size.x()` by `size.z()` from the origin, with
+/// heights scaled by `size.y()`. Each grid cell is split into two triangles, shaded with smoothly
+/// interpolated normals.
+pub struct Heightfield {
+    map: HeightMap,
+    size: Vec3,
+    normals: Vec<Vec3>,
+    levels: Vec<MipLevel>, // Level 0 has one entry per cell; each level halves the resolution.
+    material: Arc<dyn Material>
+}
+
+impl Heightfield {
+    pub fn new(map: HeightMap, size: Vec3, material: Arc<dyn Material>) -> Self {
+        let (w, d) = (map.width(), map.depth());
+        let normals = (0..d).flat_map(|z| (0..w).map(move |x| (x, z)))
+            .map(|(x, z)| {
+                let dx = (map.at((x + 1).min(w - 1), z) - map.at(x.saturating_sub(1), z)) * size.y()
+                    / (((x + 1).min(w - 1) - x.saturating_sub(1)) as f32 * size.x() / (w - 1) as f32);
+                let dz = (map.at(x, (z + 1).min(d - 1)) - map.at(x, z.saturating_sub(1))) * size.y()
+                    / (((z + 1).min(d - 1) - z.saturating_sub(1)) as f32 * size.z() / (d - 1) as f32);
+                unit_vector(&Vec3::new(-dx, 1.0, -dz))
+            })
+            .collect();
+
+        let mut levels = vec![MipLevel {
+            width: w - 1,
+            depth: d - 1,
+            ranges: (0..d - 1).flat_map(|z| (0..w - 1).map(move |x| (x, z)))
+                .map(|(x, z)| {
+                    let hs = [map.at(x, z), map.at(x + 1, z), map.at(x, z + 1), map.at(x + 1, z + 1)];
+                    (hs.iter().copied().fold(f32::INFINITY, f32::min), hs.iter().copied().fold(f32::NEG_INFINITY, f32::max))
+                })
+                .collect()
+        }];
+        while let Some(prev) = levels.last().filter(|l| l.width > 1 || l.depth > 1) {
+            let (width, depth) = (prev.width.div_ceil(2), prev.depth.div_ceil(2));
+            let mut ranges = vec![(f32::INFINITY, f32::NEG_INFINITY); width * depth];
+            for z in 0..prev.depth {
+                for x in 0..prev.width {
+                    let (lo, hi) = prev.ranges[z * prev.width + x];
+                    let r = &mut ranges[(z / 2) * width + x / 2];
+                    *r = (r.0.min(lo), r.1.max(hi));
+                }
+            }
+            levels.push(MipLevel { width, depth, ranges });
+        }
+
+        Self { map, size, normals, levels, material }
+    }
+
+    fn cell_size(&self) -> (f32, f32) {
+        (self.size.x() / (self.map.width() - 1) as f32, self.size.z() / (self.map.depth() - 1) as f32)
+    }
+
+    fn vertex(&self, x: usize, z: usize) -> Point3 {
+        let (dx, dz) = self.cell_size();
+        Point3::new(x as f32 * dx, self.map.at(x, z) * self.size.y(), z as f32 * dz)
+    }
+
+    /// Finds the nearest hit within the block `(x, z)` of `level`, returning the distance, cell
+    /// and barycentric coordinates relative to that cell's triangle.
+    fn traverse(&self, level: usize, x: usize, z: usize, r: &Ray, t_min: f32, t_max: f32) -> Option<(f32, [usize; 3], [f32; 3])> {
+        let mip = &self.levels[level];
+        let (lo, hi) = mip.ranges[z * mip.width + x];
+        let (dx, dz) = self.cell_size();
+        let (cells_w, cells_d) = (self.levels[0].width, self.levels[0].depth);
+        let span = 1 << level;
+        let bounds = Aabb::from_points(&[
+            Point3::new((x * span) as f32 * dx, lo * self.size.y(), (z * span) as f32 * dz),
+            Point3::new(((x + 1) * span).min(cells_w) as f32 * dx, hi * self.size.y(), ((z + 1) * span).min(cells_d) as f32 * dz),
+        ]);
+        if !bounds.hit(r, t_min, t_max) { return None }
+
+        if level == 0 {
+            let w = self.map.width();
+            let (a, b, c, d) = (z * w + x, z * w + x + 1, (z + 1) * w + x, (z + 1) * w + x + 1);
+            let mut best = None;
+            let mut closest = t_max;
+            for tri in [[a, b, d], [a, d, c]] {
+                let p = tri.map(|i| self.vertex(i % w, i / w));
+                if let Some((t, u, v)) = hit_triangle(r, &p, t_min, closest) {
+                    closest = t;
+                    best = Some((t, tri, [1.0 - u - v, u, v]));
+                }
+            }
+            return best
+        }
+
+        let below = &self.levels[level - 1];
+        let mut best = None;
+        let mut closest = t_max;
+        for (cx, cz) in [(2 * x, 2 * z), (2 * x + 1, 2 * z), (2 * x, 2 * z + 1), (2 * x + 1, 2 * z + 1)] {
+            if cx >= below.width || cz >= below.depth { continue }
+            if let Some(hit) = self.traverse(level - 1, cx, cz, r, t_min, closest) {
+                closest = hit.0;
+                best = Some(hit);
+            }
+        }
+        best
+    }
+}
+
+/// Möller-Trumbore intersection, returning the distance and barycentric coordinates of the 2nd
+/// and 3rd points.
+fn hit_triangle(r: &Ray, p: &[Point3; 3], t_min: f32, t_max: f32) -> Option<(f32, f32, f32)> {
+    let (e1, e2) = (p[1] - p[0], p[2] - p[0]);
+    let pvec = cross(&r.direction(), &e2);
+    let det = dot(&e1, &pvec);
+    if det.abs() < 1e-12 { return None }
+
+    let inv_det = 1.0 / det;
+    let tvec = r.origin() - p[0];
+    let u = dot(&tvec, &pvec) * inv_det;
+    if !(0.0..=1.0).contains(&u) { return None }
+
+    let qvec = cross(&tvec, &e1);
+    let v = dot(&r.direction(), &qvec) * inv_det;
+    if v < 0.0 || u + v > 1.0 { return None }
+
+    let t = dot(&e2, &qvec) * inv_det;
+    (t_min <= t && t <= t_max).then_some((t, u, v))
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (t, tri, bary) = self.traverse(self.levels.len() - 1, 0, 0, r, t_min, t_max)?;
+
+        let normal = unit_vector(&(0..3).fold(Vec3::default(), |n, i| n + bary[i] * self.normals[tri[i]]));
+        let p = r.at(t);
+        let mut rec = HitRecord::new(p, normal, self.material.clone(), t, true);
+        rec.set_face_normal(r, &normal);
+        (rec.u, rec.v) = (p.x() / self.size.x(), p.z() / self.size.z());
+        rec.tangent = Vec3::new(1.0, 0.0, 0.0);
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (lo, hi) = self.levels[self.levels.len() - 1].ranges[0];
+        Some(Aabb::from_points(&[
+            Point3::new(0.0, lo * self.size.y(), 0.0),
+            Point3::new(self.size.x(), hi * self.size.y(), self.size.z()),
+        ]))
+    }
+}
@@ -1,9 +1,11 @@
 use std::sync::Arc;
 
+use crate::aabb::Aabb;
 use crate::material::Material;
+use crate::sampler::Sampler;
 
 use super::ray::*;
-use super::{Vec3, dot, Point3};
+use super::{Vec3, cross, dot, unit_vector, Point3};
 
 #[derive(Clone)]
 pub struct HitRecord {
@@ -11,20 +13,97 @@ pub struct HitRecord {
     pub normal: Vec3,
     pub material: Arc<dyn Material>,
     pub t: f32,
-    pub front_face: bool
+    pub u: f32, pub v: f32, // Surface coordinates, for texturing.
+    pub tangent: Vec3, // Direction of increasing `u`, where the surface defines one (otherwise zero).
+    pub front_face: bool,
+    pub object_id: usize // Which of the world's objects was hit, counting from 1 (see `HittableList`).
 }
 
 impl HitRecord {
     pub fn new(p: Point3, normal: Vec3, material: Arc<dyn Material>, t: f32, front_face: bool) -> Self {
-        Self { p, normal, material, t, front_face }
+        Self { p, normal, material, t, u: 0.0, v: 0.0, tangent: Vec3::default(), front_face, object_id: 0 }
     }
 
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: &Vec3) {
         self.front_face = dot(&r.direction(), outward_normal) < 0.0;
         self.normal = if self.front_face { *outward_normal } else { -*outward_normal }
     }
+
+    /// Identifies the material, for as long as it exists. Hits on the same material (even via
+    /// different objects) share it.
+    pub fn material_key(&self) -> usize { Arc::as_ptr(&self.material) as *const () as usize }
+
+    /// An orthonormal tangent, bitangent and normal at the hit. The tangent follows `tangent` as
+    /// closely as possible, or is arbitrary if the surface doesn't define one.
+    pub fn tangent_frame(&self) -> (Vec3, Vec3, Vec3) {
+        let n = self.normal;
+        let mut t = self.tangent - dot(&self.tangent, &n) * n;
+        if t.length_squared() < 1e-12 {
+            let a = if n.x().abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+            t = cross(&a, &n);
+        }
+        let t = unit_vector(&t);
+        (t, cross(&n, &t), n)
+    }
 }
 
 pub trait Hittable: Sync + Send {
     fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
-}
\ No newline at end of file
+
+    /// Returns a box enclosing the object, or `None` if the object is unbounded.
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    /// The probability density (with respect to solid angle) that `random` produces the
+    /// direction `v` from `origin`. Objects which can't be sampled as lights return zero.
+    fn pdf_value(&self, _origin: &Point3, _v: &Vec3) -> f32 { 0.0 }
+
+    /// A random direction from `origin` towards some point on the object's surface, chosen
+    /// using `sampler`.
+    fn random(&self, _origin: &Point3, _sampler: &mut dyn Sampler) -> Vec3 { Vec3::new(1.0, 0.0, 0.0) }
+}
+
+// Allows a single shared object (e.g. a mesh's BVH) to be wrapped by many instances.
+impl<H: Hittable + ?Sized> Hittable for Arc<H> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        (**self).hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> { (**self).bounding_box() }
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f32 { (**self).pdf_value(origin, v) }
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 { (**self).random(origin, sampler) }
+}
+/// A span of a ray lying inside a solid, between the surfaces where it enters and exits.
+#[derive(Clone)]
+pub struct Span {
+    pub enter: HitRecord,
+    pub exit: HitRecord
+}
+
+/// A closed object with a well-defined inside, which can be combined with others using CSG.
+///
+/// The surface crossings reported by `hit` must alternate between entering (`front_face`) and
+/// exiting the object.
+pub trait Solid: Hittable {
+    /// Every span of `r` (over all `t`, including negative) which lies inside the object,
+    /// in increasing order of `t`.
+    fn intervals(&self, r: &Ray) -> Vec<Span> {
+        const EPSILON: f32 = 1e-4;
+        let mut spans = vec![];
+        let mut enter: Option<HitRecord> = None;
+        let mut t = f32::NEG_INFINITY;
+
+        while let Some(rec) = self.hit(r, t, f32::INFINITY) {
+            t = rec.t + EPSILON.max(rec.t.abs() * 1e-6);
+            match (rec.front_face, enter.take()) {
+                (true, _) => enter = Some(rec),
+                (false, Some(e)) => spans.push(Span { enter: e, exit: rec }),
+                (false, None) => {} // Stray exit, e.g. from grazing an edge.
+            }
+        }
+        spans
+    }
+}
+
+impl<S: Solid + ?Sized> Solid for Arc<S> {
+    fn intervals(&self, r: &Ray) -> Vec<Span> { (**self).intervals(r) }
+}
@@ -1,19 +1,50 @@
 use std::sync::Arc;
 
+use crate::aabb::{Aabb, surrounding_box};
 use crate::hittable::*;
+use crate::sampler::Sampler;
+use crate::vec3::{Point3, Vec3};
 
+/// A collection of objects, such as the world.
+///
+/// Hits are labelled with the position of the object hit in the list, counting from 1, to
+/// identify the object in the output passes. Lists within lists are labelled by the outermost.
+///
+/// Objects added as lights are also sampled directly, so that diffuse surfaces aim some of their
+/// scattered rays at them.
 #[derive(Clone)]
 pub struct HittableList {
-    objects: Vec<Arc<dyn Hittable>>
+    objects: Vec<Arc<dyn Hittable>>,
+    lights: Vec<Arc<dyn Hittable>>
 }
 
 impl HittableList {
     pub fn new(objects: Vec<Arc<dyn Hittable>>) -> Self {
-        Self { objects }
+        Self { objects, lights: vec![] }
     }
 
     pub fn add(&mut self, object: Arc<dyn Hittable>) { self.objects.push(object); }
     pub fn len(&self) -> usize { self.objects.len() }
+
+    /// Adds an object which gives off light (one which implements `pdf_value` and `random`).
+    pub fn add_light(&mut self, light: Arc<dyn Hittable>) {
+        self.objects.push(light.clone());
+        self.lights.push(light);
+    }
+
+    pub fn has_lights(&self) -> bool { !self.lights.is_empty() }
+
+    /// The probability density (with respect to solid angle) that `random_light_direction`
+    /// produces the direction `v` from `origin`.
+    pub fn light_pdf(&self, origin: &Point3, v: &Vec3) -> f32 {
+        self.lights.iter().map(|l| l.pdf_value(origin, v)).sum::<f32>() / self.lights.len() as f32
+    }
+
+    /// A direction from `origin` towards a point on one of the lights, each chosen equally often.
+    pub fn random_light_direction(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let i = ((sampler.get_1d() * self.lights.len() as f32) as usize).min(self.lights.len() - 1);
+        self.lights[i].random(origin, sampler)
+    }
 }
 
 impl Hittable for HittableList {
@@ -21,13 +52,20 @@ impl Hittable for HittableList {
         let mut closest_so_far = t_max;
         let mut hit_anything = None;
 
-        for object in &self.objects {
-            if let Some(hit) =  object.hit(r, t_min, closest_so_far) {
+        for (i, object) in self.objects.iter().enumerate() {
+            if let Some(mut hit) =  object.hit(r, t_min, closest_so_far) {
                 closest_so_far = hit.t;
+                hit.object_id = i + 1;
                 hit_anything = Some(hit);
             }
         }
 
         hit_anything
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut boxes = self.objects.iter().map(|o| o.bounding_box());
+        let first = boxes.next()??;
+        boxes.try_fold(first, |acc, b| Some(surrounding_box(&acc, &b?)))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,327 @@
+// A camera which traces rays through a real multi-element lens, described by a prescription of
+// spherical glass surfaces (after Kolb et al., 1995, as in pbrt's `RealisticCamera`).
+//
+// Rays start on the film and pass through the lens elements from the rear to the front,
+// refracting at each surface, and are lost if they miss an element or the aperture stop. This
+// gives the lens's true distortion, depth of field, bokeh and vignetting.
+//
+// Camera space has the film at z = 0 and the lens in front of it along +z, measured in
+// millimetres. The film is focused by moving it, using the lens's thick-lens approximation.
+
+use std::{fs, io};
+
+use crate::aperture::Aperture;
+use crate::camera::{look_basis, shutter_time, Camera};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::*;
+
+fn invalid(msg: &str) -> io::Error { io::Error::new(io::ErrorKind::InvalidData, msg.to_string()) }
+
+/// One surface of a lens, in millimetres. The prescription lists surfaces from the front (facing
+/// the scene) to the rear (facing the film).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LensElement {
+    /// The radius of the spherical surface, positive if its centre is behind it (towards the
+    /// film). Zero marks the aperture stop, which is flat.
+    pub curvature_radius: f32,
+    /// The distance along the axis to the next surface (or, for the last, to the film).
+    pub thickness: f32,
+    /// The refractive index of the medium behind the surface; zero (or one) for air.
+    pub ior: f32,
+    pub aperture_radius: f32
+}
+
+impl LensElement {
+    fn is_stop(&self) -> bool { self.curvature_radius == 0.0 }
+
+    /// The refractive index behind the surface, counting air as 1.
+    fn medium_ior(&self) -> f32 { if self.ior == 0.0 { 1.0 } else { self.ior } }
+}
+
+/// Where a ray through `(o, d)` meets the sphere of `radius` centred on the axis at `z_centre`, on
+/// the side nearest the lens's vertex, and the normal there facing back along the ray.
+fn intersect_element(radius: f32, z_centre: f32, o: &Point3, d: &Vec3) -> Option<(f32, Vec3)> {
+    let oc = *o - Vec3::new(0.0, 0.0, z_centre);
+    // In double precision, as the spheres are far larger than the distances being resolved:
+    let (ox, oy, oz) = (oc.x() as f64, oc.y() as f64, oc.z() as f64);
+    let (dx, dy, dz) = (d.x() as f64, d.y() as f64, d.z() as f64);
+    let a = dx * dx + dy * dy + dz * dz;
+    let b = 2.0 * (dx * ox + dy * oy + dz * oz);
+    let c = ox * ox + oy * oy + oz * oz - radius as f64 * radius as f64;
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 { return None }
+
+    let root = discriminant.sqrt();
+    let q = if b < 0.0 { -0.5 * (b - root) } else { -0.5 * (b + root) };
+    let (t0, t1) = ((q / a).min(c / q), (q / a).max(c / q));
+    let t = if (d.z() > 0.0) ^ (radius < 0.0) { t0 } else { t1 };
+    if t < 0.0 { return None }
+
+    let n = unit_vector(&(oc + t as f32 * *d));
+    Some((t as f32, if dot(&n, d) > 0.0 { -n } else { n }))
+}
+
+/// The direction of a ray arriving from `wi` (pointing back the way it came), refracted through a
+/// surface with normal `n` on the side of `wi`, where `eta` is the ratio of the refractive indices
+/// it leaves and enters. `None` for total internal reflection.
+fn refract(wi: &Vec3, n: &Vec3, eta: f32) -> Option<Vec3> {
+    let cos_i = dot(n, wi);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+    if sin2_t >= 1.0 { return None }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-eta * *wi + (eta * cos_i - cos_t) * *n)
+}
+
+/// Flips a point or direction between camera space (looking along +z) and the lens space used
+/// while tracing (in which the scene is along -z), as in pbrt.
+fn flip(v: &Vec3) -> Vec3 { Vec3::new(v.x(), v.y(), -v.z()) }
+
+/// The lens elements of a camera, from front to rear.
+#[derive(Clone, Debug)]
+pub struct LensSystem {
+    elements: Vec<LensElement>,
+    stop_shape: Aperture
+}
+
+impl LensSystem {
+    pub fn new(elements: Vec<LensElement>) -> Self {
+        assert!(!elements.is_empty(), "A lens needs at least one element.");
+        Self { elements, stop_shape: Aperture::Circle }
+    }
+
+    /// Reads a lens prescription, in the text format used by pbrt: one surface per line, from the
+    /// front, giving its curvature radius, thickness, refractive index and aperture diameter in
+    /// millimetres. Lines starting with `#` are comments.
+    pub fn load(fp: &str) -> io::Result<Self> {
+        let elements = fs::read_to_string(fp)?.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|line| {
+                let ns = line.split_whitespace()
+                    .map(|s| s.parse::<f32>().map_err(|_| invalid("Could not parse number.")))
+                    .collect::<io::Result<Vec<_>>>()?;
+                if ns.len() != 4 { return Err(invalid("Lens element needs a radius, thickness, index and aperture.")) }
+                Ok(LensElement { curvature_radius: ns[0], thickness: ns[1], ior: ns[2], aperture_radius: ns[3] / 2.0 })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        if elements.is_empty() { return Err(invalid("Lens has no elements.")) }
+        Ok(Self::new(elements))
+    }
+
+    /// Opens or closes the aperture stop to `diameter` millimetres.
+    pub fn with_stop_diameter(mut self, diameter: f32) -> Self {
+        for element in self.elements.iter_mut().filter(|e| e.is_stop()) { element.aperture_radius = diameter / 2.0; }
+        self
+    }
+
+    /// Sets the aperture stop so that the lens's entrance pupil (the stop as seen through the
+    /// front of the lens) is its focal length divided by `f_number` across.
+    pub fn with_f_number(self, f_number: f32) -> io::Result<Self> {
+        let Some(stop) = self.elements.iter().find(|e| e.is_stop()) else { return Ok(self) };
+        // The pupil scales with the stop, so it is measured with the stop closed down far enough
+        // to be what limits the rays, and scaled up:
+        let closed = 0.1 * stop.aperture_radius;
+        let pupil = self.with_circular_stop().with_stop_diameter(2.0 * closed).entrance_pupil_radius();
+        if pupil == 0.0 { return Err(invalid("Lens lets no light through along its axis.")) }
+        let diameter = 2.0 * closed * (self.focal_length()? / f_number / 2.0) / pupil;
+        Ok(self.with_stop_diameter(diameter))
+    }
+
+    /// The radius of the entrance pupil, found as the furthest from the axis that a ray from the
+    /// scene parallel to it can pass through the lens.
+    fn entrance_pupil_radius(&self) -> f32 {
+        let passes = |x: f32| self.trace_from_scene(&Point3::new(x, 0.0, self.front_z() + 1.0), &Vec3::new(0.0, 0.0, -1.0)).is_some();
+        let (mut lo, mut hi) = (0.0, self.elements[0].aperture_radius);
+        for _ in 0..32 {
+            let mid = 0.5 * (lo + hi);
+            if passes(mid) { lo = mid } else { hi = mid }
+        }
+        lo
+    }
+
+    /// Gives the aperture stop the given shape, scaled to its diameter.
+    pub fn with_stop_shape(mut self, shape: Aperture) -> Self {
+        self.stop_shape = shape;
+        self
+    }
+
+    /// The same lens with a plain circular stop, through which rays along the axis can pass
+    /// whatever shape the stop is given.
+    fn with_circular_stop(&self) -> Self {
+        Self { elements: self.elements.clone(), stop_shape: Aperture::Circle }
+    }
+
+    /// The distance from the film to the front of the lens.
+    fn front_z(&self) -> f32 { self.elements.iter().map(|e| e.thickness).sum() }
+
+    /// The distance from the film to the rear of the lens.
+    fn rear_z(&self) -> f32 { self.elements[self.elements.len() - 1].thickness }
+
+    /// Carries a ray (in lens space) across surface `i`, which is at `z`, going from a medium of
+    /// index `eta_i` to one of `eta_t`. `None` if it is blocked.
+    fn cross_surface(&self, i: usize, z: f32, o: &Point3, d: &Vec3, eta_i: f32, eta_t: f32) -> Option<(Point3, Vec3)> {
+        let element = &self.elements[i];
+        let (t, normal) = if element.is_stop() {
+            if d.z() == 0.0 { return None }
+            ((z - o.z()) / d.z(), None)
+        } else {
+            let (t, n) = intersect_element(element.curvature_radius, z + element.curvature_radius, o, d)?;
+            (t, Some(n))
+        };
+
+        let p = *o + t * *d;
+        let r = element.aperture_radius;
+        let open = if element.is_stop() { self.stop_shape.contains(p.x() / r, p.y() / r) }
+            else { p.x() * p.x() + p.y() * p.y() <= r * r };
+        if !open || t < 0.0 { return None }
+
+        match normal {
+            Some(n) => Some((p, refract(&unit_vector(&-*d), &n, eta_i / eta_t)?)),
+            None => Some((p, *d))
+        }
+    }
+
+    /// Traces a ray (in camera space) from the film out through the lens, giving the ray that
+    /// leaves its front, or `None` if the lens blocks it.
+    fn trace_from_film(&self, o: &Point3, d: &Vec3) -> Option<(Point3, Vec3)> {
+        let (mut o, mut d) = (flip(o), flip(d));
+        let mut z = 0.0;
+        for i in (0..self.elements.len()).rev() {
+            z -= self.elements[i].thickness;
+            let eta_t = if i > 0 { self.elements[i - 1].medium_ior() } else { 1.0 };
+            (o, d) = self.cross_surface(i, z, &o, &d, self.elements[i].medium_ior(), eta_t)?;
+        }
+        Some((flip(&o), flip(&d)))
+    }
+
+    /// Traces a ray (in camera space) from the scene in through the lens towards the film.
+    fn trace_from_scene(&self, o: &Point3, d: &Vec3) -> Option<(Point3, Vec3)> {
+        let (mut o, mut d) = (flip(o), flip(d));
+        let mut z = -self.front_z();
+        for i in 0..self.elements.len() {
+            let eta_i = if i > 0 { self.elements[i - 1].medium_ior() } else { 1.0 };
+            (o, d) = self.cross_surface(i, z, &o, &d, eta_i, self.elements[i].medium_ior())?;
+            z += self.elements[i].thickness;
+        }
+        Some((flip(&o), flip(&d)))
+    }
+
+    /// The positions (in lens space) of the principal plane and focal point of the lens as seen
+    /// from the scene and from the film, found from rays entering parallel to the axis at height
+    /// `x`. Fails if the lens blocks those rays.
+    fn thick_lens(&self, x: f32) -> io::Result<([f32; 2], [f32; 2])> {
+        let lens = self.with_circular_stop();
+        let cardinal_points = |o_in: &Point3, (o, d): (Point3, Vec3)| {
+            let focal = o.z() - o.x() / d.x() * d.z();
+            let principal = o.z() + (o_in.x() - o.x()) / d.x() * d.z();
+            (-principal, -focal)
+        };
+
+        let scene_ray = Point3::new(x, 0.0, lens.front_z() + 1.0);
+        let out = lens.trace_from_scene(&scene_ray, &Vec3::new(0.0, 0.0, -1.0))
+            .ok_or_else(|| invalid("Lens blocks rays from the scene along its axis."))?;
+        let (p0, f0) = cardinal_points(&scene_ray, out);
+
+        let film_ray = Point3::new(x, 0.0, lens.rear_z() - 1.0);
+        let out = lens.trace_from_film(&film_ray, &Vec3::new(0.0, 0.0, 1.0))
+            .ok_or_else(|| invalid("Lens blocks rays from the film along its axis."))?;
+        let (p1, f1) = cardinal_points(&film_ray, out);
+        Ok(([p0, p1], [f0, f1]))
+    }
+
+    /// The effective focal length of the lens, in millimetres.
+    pub fn focal_length(&self) -> io::Result<f32> {
+        let (p, f) = self.thick_lens(0.01 * self.elements[0].aperture_radius)?;
+        Ok((f[0] - p[0]).abs())
+    }
+
+    /// Moves the film so that objects `focus_dist` millimetres from it are in focus. Fails if
+    /// they're too close for the lens to focus on.
+    fn focus(&mut self, focus_dist: f32) -> io::Result<()> {
+        let (p, f) = self.thick_lens(0.01 * self.elements[0].aperture_radius)?;
+        let focal_length = f[0] - p[0];
+        let z = -focus_dist;
+        let c = (p[1] - z - p[0]) * (p[1] - z - 4.0 * focal_length - p[0]);
+        if c.is_nan() || c < 0.0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Lens can't focus as close as {focus_dist} mm.")))
+        }
+        let delta = 0.5 * (p[1] - z + p[0] - c.sqrt());
+        let last = self.elements.len() - 1;
+        self.elements[last].thickness += delta;
+        Ok(())
+    }
+
+    /// The radius of a disk on the rear element through which every ray reaching the scene from
+    /// a film of radius `film_radius` passes, found by tracing a grid of rays from across the film.
+    fn exit_pupil_radius(&self, film_radius: f32) -> f32 {
+        const FILM_SAMPLES: usize = 16;
+        const GRID: usize = 64;
+        let rear_radius = self.elements[self.elements.len() - 1].aperture_radius;
+        let mut radius = 0.0f32;
+        for i in 0..FILM_SAMPLES {
+            let film = Point3::new(film_radius * i as f32 / (FILM_SAMPLES - 1) as f32, 0.0, 0.0);
+            for (gx, gy) in (0..GRID).flat_map(|y| (0..GRID).map(move |x| (x, y))) {
+                let rear = Point3::new(
+                    rear_radius * (2.0 * (gx as f32 + 0.5) / GRID as f32 - 1.0),
+                    rear_radius * (2.0 * (gy as f32 + 0.5) / GRID as f32 - 1.0),
+                    self.rear_z()
+                );
+                if self.trace_from_film(&film, &(rear - film)).is_some() {
+                    radius = radius.max((rear.x() * rear.x() + rear.y() * rear.y()).sqrt());
+                }
+            }
+        }
+        if radius == 0.0 { return 0.0 }
+        // Grow it by a grid cell, so that rays just missed by the grid aren't lost:
+        (radius + 2.0 * rear_radius / GRID as f32).min(rear_radius)
+    }
+}
+
+/// A camera looking through a `LensSystem` onto a film (or sensor) of a given size. Its field of
+/// view follows from the lens's focal length and the film size.
+pub struct LensCamera {
+    lens: LensSystem,
+    origin: Point3,
+    u: Vec3, v: Vec3, w: Vec3,
+    film_width: f32, film_height: f32,
+    pupil_radius: f32,
+    scale: f32,
+    time0: f32, time1: f32
+}
+
+impl LensCamera {
+    /// A camera with its film at `look_from`, facing `look_at`, with a film `film_diagonal`
+    /// millimetres across the diagonal, focused on objects `focus_dist` away. `scale` is the
+    /// size of a millimetre in scene units (0.001 if the scene is in metres). Fails if the lens
+    /// can't be focused there, or lets no light through.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        look_from: Point3, look_at: Point3, vup: Vec3, mut lens: LensSystem, film_diagonal: f32, aspect_ratio: f32, focus_dist: f32, scale: f32, time0: f32, time1: f32
+    ) -> io::Result<Self> {
+        let (u, v, w) = look_basis(look_from, look_at, vup);
+        lens.focus(focus_dist / scale)?;
+        let film_height = film_diagonal / (aspect_ratio * aspect_ratio + 1.0).sqrt();
+        let film_width = aspect_ratio * film_height;
+        let pupil_radius = lens.exit_pupil_radius(film_diagonal / 2.0);
+        if pupil_radius == 0.0 { return Err(invalid("Lens lets no light through to the film.")) }
+        Ok(Self { lens, origin: look_from, u, v, w, film_width, film_height, pupil_radius, scale, time0, time1 })
+    }
+}
+
+impl Camera for LensCamera {
+    fn get_ray(&self, u: f32, v: f32, sampler: &mut dyn Sampler) -> Option<Ray> {
+        // The lens forms an upside-down image, so the top right of the picture is at the bottom
+        // left of the film:
+        let film = Point3::new((0.5 - u) * self.film_width, (0.5 - v) * self.film_height, 0.0);
+        let rear = self.pupil_radius * sample_in_unit_disk(sampler.get_2d()) + Vec3::new(0.0, 0.0, self.lens.rear_z());
+        let (o, d) = self.lens.trace_from_film(&film, &(rear - film))?;
+
+        let to_world = |p: &Vec3| p.x() * self.u + p.y() * self.v - p.z() * self.w;
+        Some(Ray::new(
+            self.origin + self.scale * to_world(&o),
+            to_world(&d),
+            shutter_time(self.time0, self.time1, sampler)
+        ))
+    }
+}
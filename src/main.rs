@@ -1,99 +1,360 @@
+mod aabb;
+mod aperture;
+mod bezier;
 mod bitmap;
+mod bump;
+mod bvh;
 mod camera;
+mod checkpoint;
+mod cone;
+mod csg;
+mod cuboid;
+mod curve;
+mod cylinder;
+mod denoise;
+mod disk;
+mod film;
+mod filter;
+mod hair;
+mod heightfield;
 mod hittable;
 mod hittable_list;
+mod lens;
 mod material;
+mod motion;
+mod moving_sphere;
+mod obj;
+mod onb;
+mod paraboloid;
+mod patches;
+mod pfm;
+mod plane;
+mod quad;
+mod quadric;
 mod ray;
+mod render;
+mod rng;
+mod sampler;
 mod scenes;
+mod sdf;
+mod solver;
 mod sphere;
 mod stl;
+mod subdivision;
+mod texture;
+mod tile;
+mod torus;
+mod transform;
 mod triangle;
 mod util;
 mod vec3;
+mod vox;
+mod voxel;
 
-use std::{env::args, io::Write};
-use std::time::Instant;
-use std::sync::Arc;
+use std::env::args;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
-use rayon::prelude::*;
-
-use bitmap::*;
-use camera::Camera;
-use hittable::Hittable;
-use hittable_list::HittableList;
-use ray::*;
-use util::*;
+use aperture::Aperture;
+use bitmap::Bitmap;
+use camera::{Camera, PerspectiveCamera, PhysicalCamera, Projection, View};
+use denoise::Denoiser;
+use film::{Aov, Film};
+use filter::Filter;
+use lens::{LensCamera, LensSystem};
+use render::{Crop, RenderSettings};
+use sampler::SamplerType;
+use scenes::Scene;
+use tile::{Tile, TileOrder};
 use vec3::*;
 
 
-fn ray_colour(r: &Ray, world: &Arc<HittableList>, depth: usize) -> Colour {
-    if depth == 0 { return Colour::new(0.0, 0.0, 0.0) }
+/// A path next to `file_path`, with `suffix` added to its name (e.g. `out.bmp` becomes
+/// `out_samples.bmp`).
+fn sibling_path(file_path: &str, suffix: &str) -> String {
+    let path = std::path::Path::new(file_path);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("render");
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("bmp");
+    path.with_file_name(format!("{stem}_{suffix}.{ext}")).to_string_lossy().into_owned()
+}
 
-    if let Some(hit_record) = world.hit(r, 0.001, f32::INFINITY) {
-        return if let Some((scattered, attenuation)) = hit_record.material.scatter(r, &hit_record) {
-             attenuation * ray_colour(&scattered, world, depth - 1)
-        } else { Colour::default() }
+/// Writes `region` of `film` to `file_path`, denoised by `denoiser` if given. If `composite` is
+/// set and the file already holds an image of the whole frame, the region is pasted into that
+/// instead.
+fn write_image(film: &Film, region: &Tile, file_path: &str, composite: bool, denoiser: Option<&Denoiser>, exposure: f32) -> std::io::Result<()> {
+    let mut image = match denoiser {
+        Some(denoiser) => Bitmap::new(denoiser.run(film, region).iter().map(|c| util::col_as_rgb(&(exposure * *c), 1)).collect(), region.width()),
+        None => film.to_bitmap(region, exposure)
+    };
+    if composite {
+        match std::fs::File::open(file_path).and_then(Bitmap::read) {
+            Ok(mut full) if full.width() == film.width() && full.height() == film.height() => {
+                full.paste(&image, region.x0, region.y0);
+                image = full;
+            }
+            _ => println!("\n[WARN] No image of the whole frame at {file_path} to composite into; writing the crop alone.")
+        }
     }
+    image.output(std::fs::File::create(file_path)?)
+}
 
-    let unit_direction = unit_vector(&r.direction());
-    let t = 0.5 * (unit_direction.y() + 1.0);
-    (1.0 - t) * Colour::new(1.0, 1.0, 1.0) + t * Colour::new(0.5, 0.7, 1.0)
+/// The command line: `<output path>`, followed by any of `--scene <description>`,
+/// `--camera <projection>`, `--fov <degrees>`, `--lens <prescription>`, `--aperture <shape>`,
+/// `--vignetting <strength>`, `--focus-pixel <x,y>`, `--sampler <name>`, `--filter <name>`,
+/// `--tiles <order>`, `--crop <x0,y0,x1,y1>`, `--composite`, `--time-budget <seconds>`,
+/// `--checkpoint`, `--denoise`, `--aovs <pass,...>` and `--heatmap`.
+///
+/// Any of `--focal-length <mm>`, `--f-number <n>`, `--shutter <seconds>`, `--iso <n>` and
+/// `--exposure <stops>` use a physical camera, with the defaults for the rest of its settings.
+#[derive(Default)]
+struct Args {
+    file_path: String,
+    scene: Option<String>,
+    projection: Option<Projection>,
+    fov: Option<f32>,
+    lens: Option<String>,
+    aperture: Option<Aperture>,
+    vignetting: Option<f32>,
+    focus_pixel: Option<(usize, usize)>,
+    physical_camera: Option<PhysicalCamera>,
+    sampler: Option<SamplerType>,
+    filter: Option<Filter>,
+    tile_order: Option<TileOrder>,
+    crop: Option<Crop>,
+    composite: bool,
+    time_budget: Option<Duration>,
+    checkpoint: bool,
+    denoise: bool,
+    aovs: Vec<Aov>,
+    heatmap: bool
+}
+
+impl Args {
+    fn physical_camera(&mut self) -> &mut PhysicalCamera { self.physical_camera.get_or_insert_with(PhysicalCamera::default) }
+}
+
+/// The value given after `flag`, parsed.
+fn flag_value<T: FromStr>(args: &mut impl Iterator<Item = String>, flag: &str) -> Option<T> where T::Err: std::fmt::Display {
+    let value = args.next().unwrap_or_else(|| panic!("{flag} needs a value."));
+    Some(value.parse().unwrap_or_else(|e| panic!("{e}")))
+}
+
+/// The number given after `flag`, which should be `what`.
+fn flag_number(args: &mut impl Iterator<Item = String>, flag: &str, what: &str) -> f32 {
+    args.next().and_then(|v| v.parse().ok()).unwrap_or_else(|| panic!("{flag} needs {what}."))
+}
+
+fn parse_args() -> Args {
+    let (mut file_path, mut parsed) = (None, Args::default());
+    let mut args = args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scene" => parsed.scene = flag_value(&mut args, &arg),
+            "--camera" => parsed.projection = flag_value(&mut args, &arg),
+            "--fov" => parsed.fov = Some(flag_number(&mut args, &arg, "a number of degrees")),
+            "--lens" => parsed.lens = flag_value(&mut args, &arg),
+            "--aperture" => parsed.aperture = flag_value(&mut args, &arg),
+            "--vignetting" => parsed.vignetting = Some(flag_number(&mut args, &arg, "a strength")),
+            "--focus-pixel" => parsed.focus_pixel = Some(args.next().and_then(|v| {
+                let (x, y) = v.split_once(',')?;
+                Some((x.parse().ok()?, y.parse().ok()?))
+            }).expect("--focus-pixel needs a pixel position, as x,y.")),
+            "--focal-length" => parsed.physical_camera().focal_length = flag_number(&mut args, &arg, "a length in millimetres"),
+            "--f-number" => parsed.physical_camera().f_number = flag_number(&mut args, &arg, "a number"),
+            "--shutter" => parsed.physical_camera().shutter_speed = flag_number(&mut args, &arg, "a number of seconds"),
+            "--iso" => parsed.physical_camera().iso = flag_number(&mut args, &arg, "a number"),
+            "--exposure" => parsed.physical_camera().exposure_compensation = flag_number(&mut args, &arg, "a number of stops"),
+            "--sampler" => parsed.sampler = flag_value(&mut args, &arg),
+            "--filter" => parsed.filter = flag_value(&mut args, &arg),
+            "--tiles" => parsed.tile_order = flag_value(&mut args, &arg),
+            "--crop" => parsed.crop = flag_value(&mut args, &arg),
+            "--composite" => parsed.composite = true,
+            "--time-budget" => parsed.time_budget = Some(
+                args.next().and_then(|v| Duration::try_from_secs_f32(v.parse().ok()?).ok()).expect("--time-budget needs a number of seconds.")
+            ),
+            "--checkpoint" => parsed.checkpoint = true,
+            "--denoise" => parsed.denoise = true,
+            "--aovs" => parsed.aovs = flag_value::<String>(&mut args, &arg).unwrap().split(',')
+                .map(|name| name.parse().unwrap_or_else(|e| panic!("{e}")))
+                .collect(),
+            "--heatmap" => parsed.heatmap = true,
+            _ if file_path.is_none() => file_path = Some(arg),
+            _ => panic!("Unexpected argument '{arg}'.")
+        }
+    }
+    Args { file_path: file_path.expect("Must provide a path for output."), ..parsed }
 }
 
 fn main() {
     let total_time = Instant::now();
-    let file_path = if let Some(fp) = args().nth(1) {
-        fp
-    } else { panic!("Must provide a path for output.") };
-
-    // Image Settings
-    let aspect_ratio = 16.0 / 9.0;
-    let image_width = 800;
-    let image_height = (image_width as f32 / aspect_ratio) as i32;
-    let samples_per_pixel = 250;
-    let max_depth = 50;
+    let Args { file_path, scene, projection, fov, lens, aperture: aperture_shape, vignetting, focus_pixel, physical_camera, sampler, filter, tile_order, crop, composite, time_budget, checkpoint, denoise, aovs, heatmap } = parse_args();
 
     // World
     print!("[INFO] Loading scene...");
-    let world = Arc::new(scenes::custom_model("../teapot.stl"));
+    let scene = scene.unwrap_or("custom_model ../teapot.stl".to_string());
+    let Scene { world, view } = scenes::by_name(&scene).unwrap_or_else(|e| panic!("{e}"));
     let t_load_model = total_time.elapsed();
-    let time_start = Instant::now();
     println!("Done!");
+    // The scene's own projection, unless another is chosen (in which case the scene's field of
+    // view, meant for its own projection, isn't used either):
+    let (projection, fov) = match projection {
+        Some(projection) if projection != view.projection => (projection, fov),
+        _ => (view.projection, fov.or(view.fov))
+    };
+
+    // Image Settings
+    let aspect_ratio = projection.aspect_ratio().unwrap_or(16.0 / 9.0);
+    let image_width = 800;
+    let mut settings = RenderSettings {
+        width: image_width,
+        height: (image_width as f32 / aspect_ratio) as usize,
+        max_depth: 50,
+        sampler: sampler.unwrap_or(SamplerType::Sobol),
+        seed: 0,
+        filter: filter.unwrap_or(Filter::Box { radius: 0.5 }),
+        tile_size: 32,
+        tile_order: tile_order.unwrap_or(TileOrder::Spiral),
+        max_samples: 250,
+        min_samples: 16,
+        pass_samples: 16,
+        error_threshold: Some(0.01),
+        time_budget,
+        crop,
+        aovs,
+        scene_key: String::new()
+    };
+    // With a crop, `composite` pastes it into the existing image at the output path rather than
+    // writing it alone:
+    let composite_crop = composite && crop.is_some();
+    let region = settings.region();
+    let written_aovs = settings.aovs.clone();
+    // The denoiser is guided by the albedo and normal passes, so they're recorded if it's used:
+    let denoiser = denoise.then(Denoiser::default);
+    if denoiser.is_some() {
+        for aov in [Aov::Albedo, Aov::Normal] {
+            if !settings.aovs.contains(&aov) { settings.aovs.push(aov) }
+        }
+    }
+    // The image (and a checkpoint, if asked for) are written this often while rendering. An
+    // existing checkpoint is carried on from (e.g. to finish an interrupted render, or to add
+    // samples):
+    let write_interval = Duration::from_secs(60);
+    let checkpoint_path = format!("{file_path}.checkpoint");
 
     // Camera
-    let look_from = Point3::new(-3.0, -10.0,  8.0);
-    let look_to   = Point3::new( 0.0,  0.0,  2.0);
-    let dist_to_focus = 10.0;
-    let vfov = 70.0;
-    let v_up = Vec3::new(0.0, 0.0, 1.0);
-    let aperture = 0.1;
-    let cam = Camera::new(look_from, look_to, v_up, vfov, aspect_ratio, aperture, dist_to_focus);
+    let time_start = Instant::now();
+    let View { look_from, look_at: look_to, vup: v_up, aperture, focus_dist: mut dist_to_focus, .. } = view;
+    let fov = fov.unwrap_or(projection.default_fov());
+    let aperture_shape = aperture_shape.unwrap_or(Aperture::Circle);
+    let vignetting = vignetting.unwrap_or(0.0);
+    let (shutter_open, shutter_close) = (0.0, 1.0);
+    // The size of a millimetre in scene units (here metres), for physical camera settings and lens
+    // prescriptions:
+    let millimetre = 0.001;
+    // The settings of a real camera, if given, set the field of view, aperture, shutter time and
+    // exposure instead:
+    let (fov, aperture, shutter_close, exposure) = match &physical_camera {
+        Some(p) => (p.vertical_fov(aspect_ratio), p.aperture(millimetre), shutter_open + p.shutter_speed, p.exposure()),
+        None => (fov, aperture, shutter_close, 1.0)
+    };
+    // Given a pixel (counted from the top left), the focus is set to the distance of what's seen
+    // there:
+    if let Some((x, y)) = focus_pixel.filter(|&(x, y)| x >= settings.width || y >= settings.height) {
+        println!("[WARN] Pixel ({x}, {y}) to focus on is outside the image.");
+    } else if let Some((x, y)) = focus_pixel {
+        let u = (x as f32 + 0.5) / (settings.width - 1) as f32;
+        let v = ((settings.height - 1 - y) as f32 + 0.5) / (settings.height - 1) as f32;
+        match camera::autofocus(&world, look_from, look_to, v_up, fov, aspect_ratio, (u, v), shutter_open) {
+            Some(distance) => {
+                println!("[INFO] Focusing at {distance:.3};");
+                dist_to_focus = distance;
+            }
+            None => println!("[WARN] Nothing to focus on at pixel ({x}, {y}).")
+        }
+    }
+    // With a lens prescription, its focal length sets the field of view instead, on a film of
+    // this diagonal (in mm):
+    let film_diagonal = match &physical_camera {
+        Some(p) => {
+            let (width, height) = p.sensor_size(aspect_ratio);
+            (width * width + height * height).sqrt()
+        }
+        None => 43.27
+    };
+    settings.scene_key = format!("{:?}", (
+        scene, (look_from, look_to, v_up), (projection, fov, &lens, millimetre, film_diagonal),
+        (aperture, &aperture_shape, vignetting, dist_to_focus), (shutter_open, shutter_close), &physical_camera
+    ));
+    let cam: Box<dyn Camera> = match (&lens, projection) {
+        (Some(fp), _) => {
+            let camera = LensSystem::load(fp)
+                .and_then(|lens| match &physical_camera {
+                    Some(p) => lens.with_f_number(p.f_number),
+                    None => Ok(lens)
+                })
+                .and_then(|lens| LensCamera::new(
+                    look_from, look_to, v_up, lens.with_stop_shape(aperture_shape), film_diagonal, aspect_ratio, dist_to_focus, millimetre, shutter_open, shutter_close
+                ));
+            match camera {
+                Ok(camera) => Box::new(camera),
+                Err(e) => {
+                    println!("[ERROR] Couldn't use lens {fp}: {e}");
+                    std::process::exit(1)
+                }
+            }
+        }
+        (None, Projection::Perspective) => Box::new(
+            PerspectiveCamera::new(look_from, look_to, v_up, fov, aspect_ratio, aperture, dist_to_focus, shutter_open, shutter_close)
+                .with_aperture(aperture_shape)
+                .with_vignetting(vignetting)
+        ),
+        (None, projection) => projection.create(look_from, look_to, v_up, fov, aspect_ratio, aperture, dist_to_focus, shutter_open, shutter_close)
+    };
 
     // Render
-    println!("[INFO] Beginning render;");
-    let mut bmp = Bitmap::new(vec![], image_width);
-    for j in (0..image_height).rev() {
-        print!("\r[{:>3}%] Rendering Scanline {} of {} {}", ((image_height - j) * 100) / image_height, image_height - j, image_height, throbber(j as usize));
-        let _ = std::io::stdout().flush();
-        let pixels = (0..image_width).into_par_iter().map(|i| {
-            let mut pixel_colour = Colour::default();
-            for _ in 0..samples_per_pixel {
-                let u = (i as f32 + random_double(0.0, 1.0)) / (image_width - 1) as f32;
-                let v = (j as f32 + random_double(0.0, 1.0)) / (image_height - 1) as f32;
-                let r = cam.get_ray(u, v);
-                pixel_colour += ray_colour(&r, &world, max_depth);
+    let mut film = Film::with_aovs(settings.width, settings.height, &settings.aovs);
+    if checkpoint {
+        match checkpoint::load(&checkpoint_path, &settings) {
+            Ok(saved) => {
+                println!("[INFO] Resuming from {} samples per pixel;", saved.next_sample());
+                film = saved;
             }
-           col_as_rgb(&pixel_colour, samples_per_pixel)
-        }).collect::<Vec<_>>();
-        bmp.push_slice(&pixels)
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => println!("[WARN] Couldn't resume from {checkpoint_path}: {e}")
+        }
     }
+
+    println!("[INFO] Beginning render;");
+    let mut last_write = Instant::now();
+    render::render(&world, cam.as_ref(), &settings, &mut film, |film| {
+        if last_write.elapsed() < write_interval { return }
+        write_image(film, &region, &file_path, composite_crop, denoiser.as_ref(), exposure).unwrap();
+        if checkpoint {
+            if let Err(e) = checkpoint::save(&checkpoint_path, film, &settings) {
+                println!("\n[WARN] Couldn't write checkpoint: {e}");
+            }
+        }
+        last_write = Instant::now();
+    });
     println!("Done!");
     let t_rendering = time_start.elapsed();
 
     let time_start = Instant::now();
     print!("[INFO] Writing Image... ");
-    let out_file = std::fs::File::create(&file_path).unwrap();
-    bmp.output(out_file).unwrap();
+    write_image(&film, &region, &file_path, composite_crop, denoiser.as_ref(), exposure).unwrap();
+    if heatmap {
+        let out_file = std::fs::File::create(sibling_path(&file_path, "samples")).unwrap();
+        film.sample_heatmap(&region).output(out_file).unwrap();
+    }
+    for aov in Aov::ALL.into_iter().filter(|aov| written_aovs.contains(aov)) {
+        let path = std::path::Path::new(&sibling_path(&file_path, aov.name())).with_extension("pfm");
+        pfm::write(std::fs::File::create(path).unwrap(), region.width(), &film.aov(aov, &region).unwrap(), aov.channels()).unwrap();
+    }
+    if checkpoint {
+        if let Err(e) = checkpoint::save(&checkpoint_path, &film, &settings) {
+            println!("\n[WARN] Couldn't write checkpoint: {e}");
+        }
+    }
     println!("Done!");
     let t_writing = time_start.elapsed();
 
@@ -104,6 +365,7 @@ fn main() {
         Time to load model:  \t{:>8.3}s\n\
         Time to render image:\t{:>8.3}s\n\
         Time to write image: \t{:>8.3}s\n\
+        Samples per pixel:   \t{:>8.1} (at most {})\n\
         Shapes in scene:     \t{:>4}",
         
         file_path,
@@ -111,6 +373,8 @@ fn main() {
         t_load_model.as_secs_f32(),
         t_rendering.as_secs_f32(),
         t_writing.as_secs_f32(),
+        film.mean_samples(&region),
+        film.next_sample(),
         world.len()
     );
-}
\ No newline at end of file
+}
@@ -1,8 +1,27 @@
-use crate::{ray::Ray, hittable::HitRecord, vec3::*, util::random_double};
+use std::f32::consts::PI;
+
+use crate::{ray::Ray, hittable::HitRecord, sampler::Sampler, vec3::*};
 
 
 pub trait Material: Sync + Send {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Colour)>;
+    /// Returns the scattered ray and its attenuation, drawing any random choices from `sampler`,
+    /// or `None` if the ray is absorbed.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<(Ray, Colour)>;
+
+    /// The probability density (with respect to solid angle) that `scatter` picks the direction
+    /// of `scattered`. Materials which give one can have rays aimed elsewhere (e.g. at lights)
+    /// instead, with the same attenuation weighted by this density. Those which can't, such as
+    /// mirrors and glass, return zero.
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f32 { 0.0 }
+
+    /// The light given off by the surface, which is none for most materials.
+    fn emitted(&self, _rec: &HitRecord) -> Colour { Colour::default() }
+
+    /// The surface's overall colour, ignoring lighting, for the albedo output pass.
+    fn albedo(&self, rec: &HitRecord) -> Colour;
+
+    /// The normal used for shading, where the material perturbs the surface's own.
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 { rec.normal }
 }
 
 pub struct Lambertian { albedo: Colour }
@@ -13,15 +32,22 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Colour)> {
-        let mut scatter_direction = rec.normal + random_unit_vector();
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<(Ray, Colour)> {
+        let mut scatter_direction = rec.normal + sample_unit_sphere(sampler.get_2d());
 
         if scatter_direction.near_zero() {
             scatter_direction = rec.normal;
         }
 
-        Some((Ray::new(rec.p, scatter_direction), self.albedo))
+        Some((Ray::new(rec.p, scatter_direction, r_in.time()), self.albedo))
     }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        let cosine = dot(&rec.normal, &unit_vector(&scattered.direction()));
+        cosine.max(0.0) / PI
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Colour { self.albedo }
 }
 
 pub struct Metal { albedo: Colour, fuzz: f32 }
@@ -32,9 +58,9 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Colour)> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<(Ray, Colour)> {
         let reflected = reflect(unit_vector(&r_in.direction()), rec.normal);
-        let scattered = Ray::new(rec.p, reflected + self.fuzz * random_in_unit_sphere());
+        let scattered = Ray::new(rec.p, reflected + self.fuzz * sample_in_unit_ball(sampler.get_2d(), sampler.get_1d()), r_in.time());
         let attenuation = self.albedo;
 
         if dot(&scattered.direction(), &rec.normal) > 0.0 {
@@ -43,6 +69,8 @@ impl Material for Metal {
             None
         }
     }
+
+    fn albedo(&self, _rec: &HitRecord) -> Colour { self.albedo }
 }
 
 pub struct Dielectric { refractive_index: f32 }
@@ -60,7 +88,7 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Colour)> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<(Ray, Colour)> {
         let refraction_ratio = if rec.front_face { 
             1.0 / self.refractive_index
         } else { 
@@ -72,7 +100,7 @@ impl Material for Dielectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let must_reflect = self.reflectance(cos_theta, refraction_ratio) > random_double(0.0, 1.0);
+        let must_reflect = self.reflectance(cos_theta, refraction_ratio) > sampler.get_1d();
         
         let direction = if cannot_refract || must_reflect {
             reflect(unit_direction, rec.normal)
@@ -80,6 +108,28 @@ impl Material for Dielectric {
             refract(unit_direction, rec.normal, refraction_ratio)
         };
 
-        Some((Ray::new(rec.p, direction), Colour::new(1.0, 1.0, 1.0)))
+        Some((Ray::new(rec.p, direction, r_in.time()), Colour::new(1.0, 1.0, 1.0)))
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Colour { Colour::new(1.0, 1.0, 1.0) }
+}
+
+/// A surface which gives off light, and reflects none.
+pub struct DiffuseLight { emit: Colour }
+impl DiffuseLight {
+    pub fn new(emit: Colour) -> Self {
+        Self { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _sampler: &mut dyn Sampler) -> Option<(Ray, Colour)> { None }
+
+    fn emitted(&self, rec: &HitRecord) -> Colour {
+        if rec.front_face { self.emit } else { Colour::default() }
     }
-}
\ No newline at end of file
+
+    fn albedo(&self, _rec: &HitRecord) -> Colour {
+        Colour::new(self.emit.x().min(1.0), self.emit.y().min(1.0), self.emit.z().min(1.0))
+    }
+}
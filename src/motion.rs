@@ -0,0 +1,177 @@
+use crate::aabb::{Aabb, surrounding_box};
+use crate::hittable::*;
+use crate::ray::Ray;
+use crate::transform::{hit_transformed, Matrix4, Transform};
+use crate::util::degrees_to_radians;
+use crate::vec3::*;
+
+/// A unit quaternion, used to interpolate rotations smoothly between keyframes.
+#[derive(Clone, Copy, Debug)]
+pub struct Quaternion { w: f32, v: Vec3 }
+
+impl Default for Quaternion {
+    fn default() -> Self { Self { w: 1.0, v: Vec3::default() } }
+}
+
+impl Quaternion {
+    /// A counter-clockwise rotation of `degrees` about `axis`.
+    pub fn from_axis_angle(axis: Vec3, degrees: f32) -> Self {
+        let (sin, cos) = (degrees_to_radians(degrees) / 2.0).sin_cos();
+        Self { w: cos, v: sin * unit_vector(&axis) }
+    }
+
+    fn dot(&self, other: &Quaternion) -> f32 { self.w * other.w + dot(&self.v, &other.v) }
+
+    fn normalised(self) -> Self {
+        let len = self.dot(&self).sqrt();
+        Self { w: self.w / len, v: self.v / len }
+    }
+
+    /// Spherical linear interpolation, always taking the shortest path.
+    pub fn slerp(&self, other: &Quaternion, t: f32) -> Self {
+        let mut cos_theta = self.dot(other);
+        let mut other = *other;
+        if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            other = Self { w: -other.w, v: -other.v };
+        }
+
+        // Nearly parallel - linear interpolation is accurate and avoids dividing by ~0:
+        if cos_theta > 0.9995 {
+            return Self { w: self.w + t * (other.w - self.w), v: self.v + t * (other.v - self.v) }.normalised();
+        }
+
+        let theta = cos_theta.acos();
+        let a = ((1.0 - t) * theta).sin() / theta.sin();
+        let b = (t * theta).sin() / theta.sin();
+        Self { w: a * self.w + b * other.w, v: a * self.v + b * other.v }
+    }
+
+    /// The angle (in radians) of the rotation taking `self` to `other`.
+    fn angle_to(&self, other: &Quaternion) -> f32 {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+
+    pub fn to_transform(self) -> Transform {
+        let (w, x, y, z) = (self.w, self.v.x(), self.v.y(), self.v.z());
+        Transform::from_rotation(Matrix4::new([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y), 0.0],
+            [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x), 0.0],
+            [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]))
+    }
+}
+
+/// An object's placement at one instant: scaled, then rotated, then translated.
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    pub time: f32,
+    pub translate: Vec3,
+    pub rotate: Quaternion,
+    pub scale: Vec3
+}
+
+impl Keyframe {
+    pub fn new(time: f32, translate: Vec3, rotate: Quaternion, scale: Vec3) -> Self {
+        Self { time, translate, rotate, scale }
+    }
+
+    fn lerp(&self, other: &Keyframe, time: f32) -> Self {
+        let s = (time - self.time) / (other.time - self.time);
+        Self {
+            time,
+            translate: self.translate + s * (other.translate - self.translate),
+            rotate: self.rotate.slerp(&other.rotate, s),
+            scale: self.scale + s * (other.scale - self.scale)
+        }
+    }
+
+    pub fn to_transform(self) -> Transform {
+        Transform::scale(self.scale)
+            .then(&self.rotate.to_transform())
+            .then(&Transform::translate(self.translate))
+    }
+}
+
+/// A transform which varies over time, interpolating between keyframes.
+///
+/// Before the first and after the last keyframe, the object holds its position.
+#[derive(Clone, Debug)]
+pub struct AnimatedTransform { keyframes: Vec<Keyframe> }
+
+impl AnimatedTransform {
+    /// Panics if no keyframes are given.
+    pub fn keyframed(mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "An animation needs at least one keyframe.");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { keyframes }
+    }
+
+    /// Moves linearly from `start` to `end`.
+    pub fn linear(start: Keyframe, end: Keyframe) -> Self { Self::keyframed(vec![start, end]) }
+
+    pub fn at(&self, time: f32) -> Keyframe {
+        let (first, last) = (self.keyframes[0], self.keyframes[self.keyframes.len() - 1]);
+        if time <= first.time { return first }
+        if time >= last.time { return last }
+
+        let i = self.keyframes.partition_point(|k| k.time <= time);
+        self.keyframes[i - 1].lerp(&self.keyframes[i], time)
+    }
+
+    pub fn transform_at(&self, time: f32) -> Transform { self.at(time).to_transform() }
+
+    /// A box containing `b` at every point during the animation.
+    ///
+    /// Each segment between keyframes is sampled, and the result padded by the furthest a
+    /// rotating corner can stray from the straight line between two samples.
+    pub fn bounding_box(&self, b: &Aabb) -> Aabb {
+        const STEPS: usize = 16;
+        let bound_at = |k: &Keyframe| k.to_transform().bounding_box(b);
+        let mut result = bound_at(&self.keyframes[0]);
+
+        for pair in self.keyframes.windows(2) {
+            let (k0, k1) = (pair[0], pair[1]);
+            let max_scale = [k0.scale, k1.scale].iter()
+                .flat_map(|s| [s.x().abs(), s.y().abs(), s.z().abs()])
+                .fold(0.0, f32::max);
+            let radius = max_scale * b.corners().iter().map(|c| c.length()).fold(0.0, f32::max);
+            let step_angle = k0.rotate.angle_to(&k1.rotate) / STEPS as f32;
+            let pad = radius * (1.0 - (step_angle / 2.0).cos());
+            let pad = Vec3::new(pad, pad, pad);
+
+            for i in 1..=STEPS {
+                let k = k0.lerp(&k1, k0.time + (k1.time - k0.time) * i as f32 / STEPS as f32);
+                let sample = bound_at(&k);
+                let padded = Aabb::new(sample.min() - pad, sample.max() + pad);
+                result = surrounding_box(&result, &padded);
+            }
+        }
+        result
+    }
+}
+
+/// Wraps some object, moving it according to an `AnimatedTransform` evaluated at each ray's time.
+pub struct Animated<H: Hittable> {
+    object: H,
+    animation: AnimatedTransform
+}
+
+impl<H: Hittable> Animated<H> {
+    pub fn new(object: H, animation: AnimatedTransform) -> Self {
+        Self { object, animation }
+    }
+}
+
+impl<H: Hittable> Hittable for Animated<H> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_transformed(&self.object, &self.animation.transform_at(r.time()), r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.object.bounding_box().map(|b| self.animation.bounding_box(&b))
+    }
+}
+
+impl<H: Solid> Solid for Animated<H> {}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use crate::aabb::{Aabb, surrounding_box};
+use crate::hittable::*;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sphere::hit_sphere;
+use crate::vec3::*;
+
+/// A sphere which moves linearly from `centre0` at `time0` to `centre1` at `time1`.
+///
+/// Outside of that interval the sphere stays at the nearest end point.
+pub struct MovingSphere {
+    centre0: Point3, centre1: Point3,
+    time0: f32, time1: f32,
+    radius: f32,
+    material: Arc<dyn Material>
+}
+
+impl MovingSphere {
+    pub fn new(centre0: Point3, centre1: Point3, time0: f32, time1: f32, radius: f32, material: Arc<dyn Material>) -> Self {
+        Self { centre0, centre1, time0, time1, radius, material }
+    }
+
+    pub fn centre(&self, time: f32) -> Point3 {
+        if self.time1 <= self.time0 { return self.centre0 }
+        let s = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        self.centre0 + s * (self.centre1 - self.centre0)
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_sphere(self.centre(r.time()), self.radius, &self.material, r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        let box0 = Aabb::from_points(&[self.centre0 - r, self.centre0 + r]);
+        let box1 = Aabb::from_points(&[self.centre1 - r, self.centre1 + r]);
+        Some(surrounding_box(&box0, &box1))
+    }
+}
+
+impl Solid for MovingSphere {}
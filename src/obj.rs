@@ -0,0 +1,97 @@
+use std::{fs, io};
+
+use crate::subdivision::PolyMesh;
+use crate::vec3::Point3;
+
+/// A triangle's corners, and their texture coordinates if it has them.
+pub type UvTriangle = ([Point3; 3], Option<[(f32, f32); 3]>);
+
+/// The vertices, texture coordinates and faces of a Wavefront OBJ file. Normals, groups and
+/// materials are ignored.
+#[derive(Clone, Debug)]
+pub struct ObjFile {
+    pub positions: Vec<Point3>,
+    pub texture_coords: Vec<(f32, f32)>,
+    /// The position of each corner of each face, and its texture coordinates if the face gives
+    /// them for every corner.
+    pub faces: Vec<(Vec<usize>, Option<Vec<usize>>)>
+}
+
+impl ObjFile {
+    /// The faces as a mesh, keeping quads and other polygons intact so that they can be
+    /// subdivided. Texture coordinates aren't kept.
+    pub fn mesh(&self) -> PolyMesh {
+        PolyMesh::new(self.positions.clone(), self.faces.iter().map(|(f, _)| f.clone()).collect())
+    }
+
+    /// The faces split into triangles, each with the texture coordinates of its corners where the
+    /// file gives them.
+    pub fn triangles(&self) -> Vec<UvTriangle> {
+        self.faces.iter().flat_map(|(face, uvs)| (1..face.len() - 1).map(move |i| {
+            let corners = [0, i, i + 1];
+            (corners.map(|c| self.positions[face[c]]), uvs.as_ref().map(|uvs| corners.map(|c| self.texture_coords[uvs[c]])))
+        })).collect()
+    }
+}
+
+/// Reads the vertices and faces of a Wavefront OBJ file as a mesh, keeping quads and other
+/// polygons intact so that they can be subdivided.
+pub fn import(fp: &str) -> io::Result<PolyMesh> {
+    Ok(read(fp)?.mesh())
+}
+
+/// Reads a Wavefront OBJ file, including its texture coordinates.
+pub fn read(fp: &str) -> io::Result<ObjFile> {
+    parse(&fs::read_to_string(fp)?)
+}
+
+fn invalid(msg: &str) -> io::Error { io::Error::new(io::ErrorKind::InvalidData, msg.to_string()) }
+
+/// Parses an index counting from 1, or backwards from the latest of `count` items if negative.
+fn index(word: &str, count: usize) -> io::Result<usize> {
+    let i: i64 = word.parse().map_err(|_| invalid("Could not parse face."))?;
+    let index = if i < 0 { count as i64 + i } else { i - 1 };
+    if index < 0 || index >= count as i64 { return Err(invalid("Face refers to a missing vertex.")) }
+    Ok(index as usize)
+}
+
+fn parse(text: &str) -> io::Result<ObjFile> {
+    let mut obj = ObjFile { positions: vec![], texture_coords: vec![], faces: vec![] };
+
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("v") => {
+                let ns = words.take(3)
+                    .map(|w| w.parse::<f32>().map_err(|_| invalid("Could not parse vertex.")))
+                    .collect::<io::Result<Vec<_>>>()?;
+                if ns.len() < 3 { return Err(invalid("Vertex needs three coordinates.")) }
+                obj.positions.push(Point3::new(ns[0], ns[1], ns[2]));
+            }
+            Some("vt") => {
+                let ns = words.take(2)
+                    .map(|w| w.parse::<f32>().map_err(|_| invalid("Could not parse texture coordinates.")))
+                    .collect::<io::Result<Vec<_>>>()?;
+                if ns.is_empty() { return Err(invalid("Texture coordinates need at least one value.")) }
+                obj.texture_coords.push((ns[0], ns.get(1).copied().unwrap_or(0.0)));
+            }
+            Some("f") => {
+                // Each vertex is `v`, `v/vt`, `v//vn` or `v/vt/vn`:
+                let (mut face, mut uvs) = (vec![], vec![]);
+                for w in words {
+                    let mut parts = w.split('/');
+                    face.push(index(parts.next().unwrap_or(""), obj.positions.len())?);
+                    match parts.next() {
+                        Some(vt) if !vt.is_empty() => uvs.push(index(vt, obj.texture_coords.len())?),
+                        _ => {}
+                    }
+                }
+                if face.len() < 3 { return Err(invalid("Face needs at least three vertices.")) }
+                let uvs = (uvs.len() == face.len()).then_some(uvs);
+                obj.faces.push((face, uvs));
+            }
+            _ => {}
+        }
+    }
+    Ok(obj)
+}
@@ -0,0 +1,29 @@
+use crate::vec3::*;
+
+/// An orthonormal basis, with `w` typically aligned to a surface normal.
+#[derive(Clone, Copy, Debug)]
+pub struct Onb { axis: [Vec3; 3] }
+
+impl Onb {
+    pub fn build_from_w(n: &Vec3) -> Self {
+        let w = unit_vector(n);
+        let a = if w.x().abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+        let v = unit_vector(&cross(&w, &a));
+        let u = cross(&w, &v);
+        Self { axis: [u, v, w] }
+    }
+
+    pub fn u(&self) -> Vec3 { self.axis[0] }
+    pub fn v(&self) -> Vec3 { self.axis[1] }
+    pub fn w(&self) -> Vec3 { self.axis[2] }
+
+    /// Converts `a`, given in this basis' local coordinates, to world space.
+    pub fn local(&self, a: &Vec3) -> Vec3 {
+        a.x() * self.u() + a.y() * self.v() + a.z() * self.w()
+    }
+
+    /// Converts `a`, given in world space, to this basis' local coordinates.
+    pub fn world_to_local(&self, a: &Vec3) -> Vec3 {
+        Vec3::new(dot(a, &self.u()), dot(a, &self.v()), dot(a, &self.w()))
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::quadric::*;
+use crate::ray::Ray;
+use crate::solver::solve_quadratic;
+use crate::vec3::*;
+
+/// A paraboloid of revolution, like a dish, with its vertex at `vertex`, opening towards `top`
+/// where it is closed off by a flat cap of `radius`.
+pub struct Paraboloid {
+    frame: Frame,
+    height: f32,
+    radius: f32,
+    material: Arc<dyn Material>
+}
+
+impl Paraboloid {
+    pub fn new(vertex: Point3, top: Point3, radius: f32, material: Arc<dyn Material>) -> Self {
+        let axis = top - vertex;
+        Self { frame: Frame::new(vertex, axis), height: axis.length(), radius, material }
+    }
+
+    /// Every intersection of the (local) ray with the paraboloid's surface, in no particular order.
+    pub fn local_hits(&self, o: &Point3, d: &Vec3) -> Vec<LocalHit> {
+        let mut hits = Vec::with_capacity(2);
+
+        // Side: x^2 + y^2 = kz, for 0 <= z <= h, where k = r^2 / h.
+        let (h, k) = (self.height, self.radius * self.radius / self.height);
+        let a = d.x() * d.x() + d.y() * d.y();
+        let b = 2.0 * (o.x() * d.x() + o.y() * d.y()) - k * d.z();
+        let c = o.x() * o.x() + o.y() * o.y() - k * o.z();
+        for t in solve_quadratic(a, b, c) {
+            let p = *o + t * *d;
+            if (0.0..=h).contains(&p.z()) {
+                let normal = unit_vector(&Vec3::new(2.0 * p.x(), 2.0 * p.y(), -k));
+                hits.push(LocalHit::new(t, normal, azimuth(&p), p.z() / h));
+            }
+        }
+
+        hits.extend(hit_cap(o, d, h, self.radius, 1.0));
+        hits
+    }
+}
+
+impl Hittable for Paraboloid {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (o, d) = self.frame.ray_to_local(r);
+        closest_hit(&self.frame, &self.local_hits(&o, &d), &self.material, r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.radius;
+        Some(self.frame.bounding_box(&Aabb::new(Point3::new(-r, -r, 0.0), Point3::new(r, r, self.height))))
+    }
+}
+
+impl Solid for Paraboloid {}
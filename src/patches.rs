@@ -0,0 +1,74 @@
+use std::{fs, io};
+
+use crate::bezier::ControlPoints;
+use crate::vec3::Point3;
+
+/// Returns the bicubic patches stored in a file using the classic Newell teapot layout:
+///
+/// ```text
+/// <number of patches>
+/// <16 comma-separated, 1-based vertex indices>   (one line per patch)
+/// <number of vertices>
+/// <x>,<y>,<z>                                    (one line per vertex)
+/// ```
+///
+/// Files ending in `.bpt` are instead read as a count, followed by each patch as a `3 3` degree
+/// line and 16 lines of whitespace-separated coordinates.
+pub fn import(fp: &str) -> io::Result<Vec<ControlPoints>> {
+    let text = fs::read_to_string(fp)?;
+    if fp.to_lowercase().ends_with(".bpt") { parse_bpt(&text) } else { parse_newell(&text) }
+}
+
+fn invalid(msg: &str) -> io::Error { io::Error::new(io::ErrorKind::InvalidData, msg.to_string()) }
+
+fn numbers<T: std::str::FromStr>(line: &str) -> io::Result<Vec<T>> {
+    line.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<T>().map_err(|_| invalid("Could not parse number.")))
+        .collect()
+}
+
+fn to_patch(points: &[Point3]) -> ControlPoints {
+    let mut cp = [[Point3::default(); 4]; 4];
+    for (k, p) in points.iter().enumerate() { cp[k / 4][k % 4] = *p; }
+    cp
+}
+
+fn parse_newell(text: &str) -> io::Result<Vec<ControlPoints>> {
+    let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+    let mut next = || lines.next().ok_or_else(|| invalid("Unexpected end of file."));
+
+    let n_patches: usize = next()?.parse().map_err(|_| invalid("Bad patch count."))?;
+    let indices = (0..n_patches).map(|_| numbers::<usize>(next()?)).collect::<io::Result<Vec<_>>>()?;
+
+    let n_vertices: usize = next()?.parse().map_err(|_| invalid("Bad vertex count."))?;
+    let vertices = (0..n_vertices).map(|_| {
+        let ns = numbers::<f32>(next()?)?;
+        if ns.len() < 3 { return Err(invalid("Vertex needs 3 coordinates.")) }
+        Ok(Point3::new(ns[0], ns[1], ns[2]))
+    }).collect::<io::Result<Vec<_>>>()?;
+
+    indices.iter().map(|patch| {
+        if patch.len() != 16 { return Err(invalid("Patch needs 16 vertices.")) }
+        let points = patch.iter()
+            .map(|&i| i.checked_sub(1).and_then(|i| vertices.get(i).copied()).ok_or_else(|| invalid("Bad vertex index.")))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(to_patch(&points))
+    }).collect()
+}
+
+fn parse_bpt(text: &str) -> io::Result<Vec<ControlPoints>> {
+    let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+    let mut next = || lines.next().ok_or_else(|| invalid("Unexpected end of file."));
+
+    let n_patches: usize = next()?.parse().map_err(|_| invalid("Bad patch count."))?;
+    (0..n_patches).map(|_| {
+        if numbers::<usize>(next()?)? != [3, 3] { return Err(invalid("Only bicubic patches are supported.")) }
+        let points = (0..16).map(|_| {
+            let ns = numbers::<f32>(next()?)?;
+            if ns.len() < 3 { return Err(invalid("Vertex needs 3 coordinates.")) }
+            Ok(Point3::new(ns[0], ns[1], ns[2]))
+        }).collect::<io::Result<Vec<_>>>()?;
+        Ok(to_patch(&points))
+    }).collect()
+}
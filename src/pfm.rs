@@ -0,0 +1,22 @@
+use std::io::{self, Write};
+
+/// Writes a Portable Float Map: an uncompressed image of 32-bit floats, which (unlike a bitmap)
+/// keeps values outside [0, 1] and their full precision.
+///
+/// `data` holds `channels` values for each pixel, row by row from the top. One channel is written
+/// as greyscale; two or three as colour, with any missing channel set to zero.
+pub fn write(mut out: impl Write, width: usize, data: &[f32], channels: usize) -> io::Result<()> {
+    let height = data.len() / (width * channels).max(1);
+    let colour = channels > 1;
+    write!(out, "{}\n{} {}\n-1.0\n", if colour { "PF" } else { "Pf" }, width, height)?;
+
+    // Rows are stored from the bottom, and the negative scale marks the values as little-endian:
+    let written = if colour { 3 } else { 1 };
+    let mut bytes = Vec::with_capacity(width * height * written * 4);
+    for row in data.chunks_exact(width * channels).rev() {
+        for pixel in row.chunks_exact(channels) {
+            for c in 0..written { bytes.extend(pixel.get(c).unwrap_or(&0.0).to_le_bytes()); }
+        }
+    }
+    out.write_all(&bytes)
+}
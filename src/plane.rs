@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::*;
+
+/// An infinite plane passing through `point`. Being unbounded, it can't be placed in a BVH.
+pub struct Plane {
+    point: Point3,
+    basis: Onb,
+    material: Arc<dyn Material>
+}
+
+impl Plane {
+    pub fn new(point: Point3, normal: Vec3, material: Arc<dyn Material>) -> Self {
+        Self { point, basis: Onb::build_from_w(&normal), material }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let normal = self.basis.w();
+        let denom = dot(&normal, &r.direction());
+        if denom.abs() < 1e-8 { return None }
+
+        let t = dot(&(self.point - r.origin()), &normal) / denom;
+        if t < t_min || t_max < t { return None }
+
+        let p = r.at(t);
+        let mut rec = HitRecord::new(p, normal, self.material.clone(), t, true);
+        rec.set_face_normal(r, &normal);
+
+        // UVs are world-space distances along the plane, so textures tile with a period of 1:
+        let offset = p - self.point;
+        rec.u = dot(&offset, &self.basis.u());
+        rec.v = dot(&offset, &self.basis.v());
+        rec.tangent = self.basis.u();
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> { None }
+}
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::*;
+
+/// A parallelogram with one corner at `q`, and edges `u` and `v`.
+pub struct Quad {
+    q: Point3,
+    u: Vec3, v: Vec3,
+    w: Vec3, // n / (n . n), used to find the planar coordinates of a hit.
+    normal: Vec3,
+    area: f32,
+    material: Arc<dyn Material>
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Self {
+        let n = cross(&u, &v);
+        Self { q, u, v, w: n / dot(&n, &n), normal: unit_vector(&n), area: n.length(), material }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let denom = dot(&self.normal, &r.direction());
+        if denom.abs() < 1e-8 { return None }
+
+        let t = dot(&(self.q - r.origin()), &self.normal) / denom;
+        if t < t_min || t_max < t { return None }
+
+        // Express the hit in terms of the edges, and check that it lies within them:
+        let p = r.at(t);
+        let planar = p - self.q;
+        let alpha = dot(&self.w, &cross(&planar, &self.v));
+        let beta = dot(&self.w, &cross(&self.u, &planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) { return None }
+
+        let mut rec = HitRecord::new(p, self.normal, self.material.clone(), t, true);
+        rec.set_face_normal(r, &self.normal);
+        rec.u = alpha;
+        rec.v = beta;
+        rec.tangent = self.u;
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::from_points(&[self.q, self.q + self.u, self.q + self.v, self.q + self.u + self.v]))
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f32 {
+        let Some(rec) = self.hit(&Ray::new(*origin, *v, 0.0), 0.001, f32::INFINITY) else { return 0.0 };
+
+        let distance_squared = rec.t * rec.t * v.length_squared();
+        let cosine = (dot(v, &rec.normal) / v.length()).abs();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (a, b) = sampler.get_2d();
+        let p = self.q + a * self.u + b * self.v;
+        p - *origin
+    }
+}
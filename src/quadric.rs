@@ -0,0 +1,84 @@
+// Support shared by the curved primitives (cylinders, cones, tori and paraboloids), which are
+// each intersected in their own local space, with the z-axis along the shape's axis.
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::HitRecord;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::*;
+
+/// A rigid local coordinate system: an origin, and an orthonormal basis with `w` along the axis.
+pub struct Frame {
+    origin: Point3,
+    basis: Onb
+}
+
+impl Frame {
+    pub fn new(origin: Point3, axis: Vec3) -> Self {
+        Self { origin, basis: Onb::build_from_w(&axis) }
+    }
+
+    /// The ray's origin and direction in local space. The frame is rigid, so distances along
+    /// the ray are unchanged.
+    pub fn ray_to_local(&self, r: &Ray) -> (Point3, Vec3) {
+        (self.basis.world_to_local(&(r.origin() - self.origin)), self.basis.world_to_local(&r.direction()))
+    }
+
+    pub fn vector_to_world(&self, v: &Vec3) -> Vec3 { self.basis.local(v) }
+
+    /// Converts a box in local space to a world-space box containing it.
+    pub fn bounding_box(&self, local: &Aabb) -> Aabb {
+        Aabb::from_points(&local.corners().map(|c| self.origin + self.basis.local(&c)))
+    }
+}
+
+/// A ray-surface intersection, with the outward normal in local space.
+#[derive(Clone, Copy, Debug)]
+pub struct LocalHit {
+    pub t: f32,
+    pub normal: Vec3,
+    pub u: f32, pub v: f32
+}
+
+impl LocalHit {
+    pub fn new(t: f32, normal: Vec3, u: f32, v: f32) -> Self { Self { t, normal, u, v } }
+}
+
+/// Picks the nearest of `hits` within the range, and converts it into a `HitRecord`.
+pub fn closest_hit(
+    frame: &Frame, hits: &[LocalHit], material: &Arc<dyn Material>, r: &Ray, t_min: f32, t_max: f32
+) -> Option<HitRecord> {
+    let hit = hits.iter()
+        .filter(|h| t_min <= h.t && h.t <= t_max)
+        .min_by(|a, b| a.t.total_cmp(&b.t))?;
+
+    let outward_normal = unit_vector(&frame.vector_to_world(&hit.normal));
+    let mut rec = HitRecord::new(r.at(hit.t), outward_normal, material.clone(), hit.t, true);
+    rec.set_face_normal(r, &outward_normal);
+    (rec.u, rec.v) = (hit.u, hit.v);
+
+    // `u` runs around the axis, so the tangent does too (except where the normal is along it):
+    let tangent = Vec3::new(-hit.normal.y(), hit.normal.x(), 0.0);
+    if tangent.length_squared() > 1e-12 { rec.tangent = frame.vector_to_world(&tangent); }
+    Some(rec)
+}
+
+/// The angle of `p` around the z-axis, scaled to [0, 1].
+pub fn azimuth(p: &Point3) -> f32 {
+    (p.y().atan2(p.x()) + PI) / (2.0 * PI)
+}
+
+/// Intersects the local ray with the disk of `radius` in the plane `z = height`, facing along
+/// `facing` (+1 or -1) on the z-axis. UVs are polar, as for `Disk`.
+pub fn hit_cap(o: &Point3, d: &Vec3, height: f32, radius: f32, facing: f32) -> Option<LocalHit> {
+    if d.z().abs() < 1e-8 { return None }
+    let t = (height - o.z()) / d.z();
+    let p = *o + t * *d;
+    let r2 = p.x() * p.x() + p.y() * p.y();
+    if r2 > radius * radius { return None }
+    Some(LocalHit::new(t, Vec3::new(0.0, 0.0, facing), azimuth(&p), r2.sqrt() / radius))
+}
@@ -4,12 +4,15 @@ use super::vec3::*;
 #[derive(Default)]
 pub struct Ray {
     orig: Point3,
-    dir: Vec3
+    dir: Vec3,
+    tm: f32
 }
 
 impl Ray {
-    pub fn new(orig: Point3, dir: Vec3) -> Self { Self { orig, dir } }
+    pub fn new(orig: Point3, dir: Vec3, tm: f32) -> Self { Self { orig, dir, tm } }
     pub fn origin(&self) -> Point3 { self.orig }
     pub fn direction(&self) -> Vec3 { self.dir }
+    /// The instant (within the camera's shutter interval) at which this ray was fired.
+    pub fn time(&self) -> f32 { self.tm }
     pub fn at(&self, t: f32) -> Point3 { self.orig + (t * self.dir) }
 }
\ No newline at end of file
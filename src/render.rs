@@ -0,0 +1,297 @@
+use std::io::Write;
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+
+use crate::camera::Camera;
+use crate::film::{Aov, AovSample, Film};
+use crate::filter::Filter;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::ray::Ray;
+use crate::rng::hash_combine;
+use crate::sampler::{Sampler, SamplerType};
+use crate::tile::{tiles, Tile, TileOrder};
+use crate::util::throbber;
+use crate::vec3::*;
+
+/// A part of the image to render by itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Crop {
+    /// A rectangle of pixels from `(x0, y0)` up to but not including `(x1, y1)`, with rows
+    /// counted from the top.
+    Pixels { x0: usize, y0: usize, x1: usize, y1: usize },
+    /// A window given as fractions of the image's width and height, from the top-left.
+    Window { x0: f32, y0: f32, x1: f32, y1: f32 }
+}
+
+/// Parses `x0,y0,x1,y1` as pixels, or as a window if any of them have a decimal point (e.g.
+/// `0.5,0,1.0,0.5` for the top-right quarter).
+impl FromStr for Crop {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Crop '{s}' should be four numbers, x0,y0,x1,y1.");
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        if parts.len() != 4 { return Err(invalid()) }
+        if s.contains('.') {
+            let n = parts.iter().map(|p| p.parse::<f32>()).collect::<Result<Vec<_>, _>>().map_err(|_| invalid())?;
+            Ok(Crop::Window { x0: n[0], y0: n[1], x1: n[2], y1: n[3] })
+        } else {
+            let n = parts.iter().map(|p| p.parse::<usize>()).collect::<Result<Vec<_>, _>>().map_err(|_| invalid())?;
+            Ok(Crop::Pixels { x0: n[0], y0: n[1], x1: n[2], y1: n[3] })
+        }
+    }
+}
+
+impl Crop {
+    /// The pixels covered by the crop, clipped to a `width` by `height` image.
+    pub fn region(&self, width: usize, height: usize) -> Tile {
+        let (x0, y0, x1, y1) = match *self {
+            Crop::Pixels { x0, y0, x1, y1 } => (x0, y0, x1, y1),
+            Crop::Window { x0, y0, x1, y1 } => {
+                let (w, h) = (width as f32, height as f32);
+                ((x0 * w).round() as usize, (y0 * h).round() as usize, (x1 * w).round() as usize, (y1 * h).round() as usize)
+            }
+        };
+        let (x1, y1) = (x1.min(width), y1.min(height));
+        Tile { x0: x0.min(x1), y0: y0.min(y1), x1, y1 }
+    }
+}
+
+/// Everything about how an image is rendered, other than the scene and camera.
+#[derive(Clone, Debug)]
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub max_depth: usize,
+    pub sampler: SamplerType,
+    pub seed: u64,
+    /// How each sample is weighted into the pixels around it.
+    pub filter: Filter,
+    /// The width and height of the tiles which the image is divided into for rendering.
+    pub tile_size: usize,
+    pub tile_order: TileOrder,
+    /// The most samples to take for any pixel.
+    pub max_samples: usize,
+    /// The fewest samples to take before a pixel may be considered converged.
+    pub min_samples: usize,
+    /// Samples are taken in passes of this many per pixel, checking for convergence in between.
+    pub pass_samples: usize,
+    /// Pixels stop being sampled once the relative error of their mean falls below this. If
+    /// `None`, every pixel takes `max_samples`.
+    pub error_threshold: Option<f32>,
+    /// If set, passes are added until this much time has passed (or every pixel has converged)
+    /// instead of stopping at `max_samples`. A pass isn't started if the last one suggests it
+    /// would finish over the budget. The stratified sampler isn't used with a budget.
+    pub time_budget: Option<Duration>,
+    /// If set, only this part of the image is rendered. Its pixels come out exactly as they
+    /// would in a render of the whole image.
+    pub crop: Option<Crop>,
+    /// The auxiliary passes to record alongside the image.
+    pub aovs: Vec<Aov>,
+    /// A description of the scene and camera, which the sample hash covers so that a checkpoint
+    /// of a different view isn't resumed.
+    pub scene_key: String
+}
+
+impl RenderSettings {
+    /// A hash of the settings which determine the value of every sample, so that a render is
+    /// only resumed with compatible ones. The sample counts and error threshold may change, except
+    /// with the stratified sampler.
+    pub fn sample_hash(&self) -> u64 {
+        // Stratified samples also depend on how many strata each pixel is divided into:
+        let sampler = self.sampler_type();
+        let strata = (sampler == SamplerType::Stratified).then_some(self.max_samples);
+        let key = format!("{:?}", (self.width, self.height, self.max_depth, sampler, strata, self.seed, self.filter, self.crop, &self.aovs, &self.scene_key));
+        key.bytes().fold(0, |h, b| hash_combine(h, b as u64))
+    }
+
+    /// The sampler actually used. The stratified sampler needs to know how many samples each pixel
+    /// will take, which isn't known with a time budget, so Sobol samples are used instead.
+    pub fn sampler_type(&self) -> SamplerType {
+        match (self.sampler, self.time_budget) {
+            (SamplerType::Stratified, Some(_)) => SamplerType::Sobol,
+            (sampler, _) => sampler
+        }
+    }
+
+    /// The pixels of the image being rendered: the crop, or else the whole image.
+    pub fn region(&self) -> Tile {
+        match self.crop {
+            Some(crop) => crop.region(self.width, self.height),
+            None => Tile { x0: 0, y0: 0, x1: self.width, y1: self.height }
+        }
+    }
+
+    /// The pixels which need sampling to render the region: it, and those around it near
+    /// enough for their samples to be splatted into it.
+    fn sampled_region(&self) -> Tile {
+        let region = self.region();
+        let margin = (self.filter.radius() - 0.5).ceil().max(0.0) as usize;
+        Tile {
+            x0: region.x0.saturating_sub(margin), y0: region.y0.saturating_sub(margin),
+            x1: (region.x1 + margin).min(self.width), y1: (region.y1 + margin).min(self.height)
+        }
+    }
+}
+
+pub fn ray_colour(r: &Ray, world: &HittableList, depth: usize, sampler: &mut dyn Sampler) -> Colour {
+    if depth == 0 { return Colour::new(0.0, 0.0, 0.0) }
+    shade(r, world.hit(r, 0.001, f32::INFINITY).as_ref(), world, depth, sampler)
+}
+
+/// The light arriving along `r`, given the surface it hits first (if any).
+fn shade(r: &Ray, hit: Option<&HitRecord>, world: &HittableList, depth: usize, sampler: &mut dyn Sampler) -> Colour {
+    if depth == 0 { return Colour::new(0.0, 0.0, 0.0) }
+    let Some(hit_record) = hit else { return background(r) };
+
+    let material = &hit_record.material;
+    let emitted = material.emitted(hit_record);
+    let Some((scattered, attenuation)) = material.scatter(r, hit_record, sampler) else { return emitted };
+    if !world.has_lights() || material.scattering_pdf(r, hit_record, &scattered) <= 0.0 {
+        return emitted + attenuation * ray_colour(&scattered, world, depth - 1, sampler)
+    }
+
+    // Aim half of the rays at the lights instead, and weight each ray by how likely the material
+    // was to scatter that way compared to how likely it was to be picked by either strategy:
+    let scattered = if sampler.get_1d() < 0.5 {
+        Ray::new(hit_record.p, world.random_light_direction(&hit_record.p, sampler), r.time())
+    } else { scattered };
+    let scattering_pdf = material.scattering_pdf(r, hit_record, &scattered);
+    let pdf = 0.5 * scattering_pdf + 0.5 * world.light_pdf(&hit_record.p, &scattered.direction());
+    if scattering_pdf <= 0.0 || pdf <= 0.0 { return emitted }
+    emitted + scattering_pdf / pdf * attenuation * ray_colour(&scattered, world, depth - 1, sampler)
+}
+
+/// The sky, seen along `r`.
+fn background(r: &Ray) -> Colour {
+    let unit_direction = unit_vector(&r.direction());
+    let t = 0.5 * (unit_direction.y() + 1.0);
+    (1.0 - t) * Colour::new(1.0, 1.0, 1.0) + t * Colour::new(0.5, 0.7, 1.0)
+}
+
+/// What the camera ray `r` saw, for the auxiliary passes.
+fn aov_sample(r: &Ray, hit: Option<&HitRecord>) -> AovSample {
+    let Some(rec) = hit else { return AovSample { albedo: background(r), ..Default::default() } };
+    AovSample {
+        depth: rec.t * r.direction().length(),
+        position: rec.p,
+        normal: rec.material.shading_normal(rec),
+        albedo: rec.material.albedo(rec),
+        object_id: rec.object_id,
+        material_key: rec.material_key(),
+        uv: (rec.u, rec.v)
+    }
+}
+
+/// Renders samples `samples` of every unconverged pixel in `tile` into a film of its own, which
+/// has a margin around the tile for the filter to splat into. Returns that film, and the position
+/// of its top-left pixel in the whole image.
+fn render_tile(
+    world: &HittableList, cam: &dyn Camera, settings: &RenderSettings, film: &Film, tile: &Tile, samples: Range<usize>
+) -> (Film, (isize, isize)) {
+    let (width, height) = (settings.width, settings.height);
+    let margin = (settings.filter.radius() + 0.5).ceil() as usize;
+    let offset = (tile.x0 as isize - margin as isize, tile.y0 as isize - margin as isize);
+    let mut local = Film::with_aovs(tile.x1 - tile.x0 + 2 * margin, tile.y1 - tile.y0 + 2 * margin, &settings.aovs);
+    let mut sampler = settings.sampler_type().create(settings.max_samples, settings.seed);
+
+    for y in tile.y0..tile.y1 {
+        for i in tile.x0..tile.x1 {
+            if film.pixel(i, y).converged { continue }
+            let j = height - 1 - y;
+            for s in samples.clone() {
+                sampler.start_sample((i, j), s);
+                let (dx, dy) = sampler.get_2d();
+                let u = (i as f32 + dx) / (width - 1) as f32;
+                let v = (j as f32 + dy) / (height - 1) as f32;
+                let r = cam.get_ray(u, v, sampler.as_mut());
+                let hit = r.as_ref().and_then(|r| world.hit(r, 0.001, f32::INFINITY));
+                let colour = match &r {
+                    Some(r) => shade(r, hit.as_ref(), world, settings.max_depth, sampler.as_mut()),
+                    None => Colour::default()
+                };
+
+                // Positions within the tile's film, whose rows run from the top, so `dy` is flipped:
+                let (lx, ly) = ((i as isize - offset.0) as usize, (y as isize - offset.1) as usize);
+                local.pixel_mut(lx, ly).add(colour);
+                if !settings.aovs.is_empty() {
+                    let aovs = r.as_ref().map_or(AovSample::default(), |r| aov_sample(r, hit.as_ref()));
+                    local.add_aovs(lx, ly, &aovs, s == 0);
+                }
+                local.splat((lx as f32 + dx, ly as f32 + 1.0 - dy), colour, &settings.filter);
+            }
+        }
+    }
+    (local, offset)
+}
+
+/// Marks which pixels have converged, i.e. have taken enough samples for the error in their
+/// mean to be below the threshold. Pixels outside the region being sampled count as converged
+/// from the start.
+fn mark_converged(film: &mut Film, settings: &RenderSettings) {
+    let sampled = settings.sampled_region();
+    for y in 0..film.height() {
+        for x in 0..film.width() {
+            let p = film.pixel_mut(x, y);
+            p.converged = !sampled.contains(x, y) || settings.error_threshold
+                .is_some_and(|threshold| p.samples >= settings.min_samples && p.relative_error() < threshold);
+        }
+    }
+}
+
+/// Adds samples to `film` in passes, each taking up to `pass_samples` more samples of every
+/// pixel which hasn't yet converged, and calling `on_pass` with the film after each. The film
+/// may already hold samples (e.g. from a checkpoint), in which case the render carries on from
+/// where it left off.
+///
+/// Within a pass the image is split into tiles which rayon's workers take in turn. The finished
+/// tiles are merged into the film in the same order every time, so that the image doesn't depend
+/// on the number of threads.
+pub fn render(world: &HittableList, cam: &dyn Camera, settings: &RenderSettings, film: &mut Film, mut on_pass: impl FnMut(&Film)) {
+    let tiles = tiles(&settings.sampled_region(), settings.tile_size, settings.tile_order);
+    mark_converged(film, settings);
+
+    let time_start = Instant::now();
+    let mut last_pass = Duration::ZERO;
+    let mut start = film.next_sample();
+    let mut pass = 1;
+    while film.total_active() > 0 {
+        let end = match settings.time_budget {
+            Some(budget) => {
+                if time_start.elapsed() + last_pass > budget { break }
+                start + settings.pass_samples.max(1)
+            }
+            None => {
+                if start >= settings.max_samples { break }
+                (start + settings.pass_samples.max(1)).min(settings.max_samples)
+            }
+        };
+        let pass_start = Instant::now();
+        let active = film.total_active();
+        let finished = AtomicUsize::new(0);
+
+        let rendered: Vec<_> = tiles.par_iter().map(|tile| {
+            let result = render_tile(world, cam, settings, film, tile, start..end);
+            let n = finished.fetch_add(1, Ordering::Relaxed) + 1;
+            print!("\r[Pass {:>3}] Samples {:>4} to {:>4}, rendered tile {} of {} ({} pixels active) {}",
+                pass, start + 1, end, n, tiles.len(), active, throbber(n));
+            let _ = std::io::stdout().flush();
+            result
+        }).collect();
+
+        for (local, offset) in &rendered { film.merge(local, *offset); }
+        film.set_next_sample(end);
+        mark_converged(film, settings);
+        on_pass(film);
+
+        last_pass = pass_start.elapsed();
+        start = end;
+        pass += 1;
+    }
+    println!();
+}
@@ -0,0 +1,49 @@
+// Deterministic random numbers. Every random choice in a render is drawn from a stream derived
+// from the global seed (and, while rendering, the pixel and sample), so that the same seed
+// always gives the same image, however the work is split between threads.
+
+/// Mixes the bits of `x` thoroughly (the finaliser of MurmurHash3).
+pub fn hash(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51afd7ed558ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ceb9fe1a85ec53);
+    x ^ (x >> 33)
+}
+
+/// Combines `value` into a hash `seed`, e.g. to derive a stream for one pixel from the global
+/// seed.
+pub fn hash_combine(seed: u64, value: u64) -> u64 {
+    hash(seed ^ value.wrapping_add(0x9e3779b97f4a7c15).wrapping_add(seed << 6).wrapping_add(seed >> 2))
+}
+
+/// A PCG32 random number generator (O'Neill's "XSH RR" variant): small, fast and statistically
+/// sound, with 2^63 independent streams for each seed.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+    inc: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self { Self::with_stream(seed, 0) }
+
+    /// A generator for one of many independent sequences from the same seed.
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Self { state: 0, inc: (stream << 1) | 1 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(6364136223846793005).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// A value in [0, 1).
+    pub fn next_f32(&mut self) -> f32 { (self.next_u32() >> 8) as f32 / (1 << 24) as f32 }
+}
@@ -0,0 +1,299 @@
+// Sample generators for the render loop: each pixel sample draws a sequence of numbers in
+// [0, 1) (for the jitter within the pixel, the lens, the shutter time, and then each bounce)
+// from a `Sampler`. Better-distributed sequences reach the same noise level with fewer samples.
+
+use std::str::FromStr;
+
+use crate::rng::{Rng, hash_combine};
+
+/// A source of sample values, consumed one dimension at a time.
+pub trait Sampler {
+    /// Starts sample `index` of pixel `(x, y)`, going back to the first dimension.
+    fn start_sample(&mut self, pixel: (usize, usize), index: usize);
+
+    fn get_1d(&mut self) -> f32;
+    fn get_2d(&mut self) -> (f32, f32);
+}
+
+/// The kinds of `Sampler` available, which can be parsed from their names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SamplerType {
+    /// Uncorrelated random numbers, as used originally.
+    Independent,
+    /// Each dimension (or pair) is divided into one stratum per sample, and each sample jittered
+    /// within a different stratum.
+    Stratified,
+    /// The Halton sequence, randomised by a different offset for each pixel.
+    Halton,
+    /// The Sobol sequence, with hash-based Owen scrambling to decorrelate pixels.
+    Sobol
+}
+
+impl FromStr for SamplerType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "independent" => Ok(SamplerType::Independent),
+            "stratified" => Ok(SamplerType::Stratified),
+            "halton" => Ok(SamplerType::Halton),
+            "sobol" => Ok(SamplerType::Sobol),
+            _ => Err(format!("Unknown sampler '{s}'; expected independent, stratified, halton or sobol."))
+        }
+    }
+}
+
+impl SamplerType {
+    /// A sampler for one pixel at a time, expecting `samples_per_pixel` samples of each. The
+    /// same `seed` always produces the same values for a given pixel and sample.
+    pub fn create(self, samples_per_pixel: usize, seed: u64) -> Box<dyn Sampler> {
+        match self {
+            SamplerType::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerType::Stratified => Box::new(StratifiedSampler::new(samples_per_pixel, seed)),
+            SamplerType::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerType::Sobol => Box::new(SobolSampler::new(seed))
+        }
+    }
+}
+
+/// A seed for one pixel's samples, derived from the global seed.
+fn pixel_seed(seed: u64, (x, y): (usize, usize)) -> u64 { hash_combine(hash_combine(seed, x as u64), y as u64) }
+
+/// The top 24 bits of `x` as a value in [0, 1).
+fn to_unit(x: u32) -> f32 { (x >> 8) as f32 / (1 << 24) as f32 }
+
+/// Draws each value from a PCG stream of its own for every pixel sample.
+pub struct IndependentSampler {
+    seed: u64,
+    rng: Rng
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self { Self { seed, rng: Rng::new(seed) } }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.rng = Rng::with_stream(pixel_seed(self.seed, pixel), index as u64);
+    }
+
+    fn get_1d(&mut self) -> f32 { self.rng.next_f32() }
+    fn get_2d(&mut self) -> (f32, f32) { (self.rng.next_f32(), self.rng.next_f32()) }
+}
+
+/// Returns element `i` of a random permutation of `0..len`, chosen by `seed`, without storing
+/// it (Kensler's "Correlated Multi-Jittered Sampling").
+fn permute(mut i: u32, len: u32, seed: u32) -> u32 {
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < len { break }
+    }
+    (i.wrapping_add(seed)) % len
+}
+
+pub struct StratifiedSampler {
+    samples: u32,
+    grid: (u32, u32), // Strata across and down, for 2D samples.
+    global_seed: u64,
+    seed: u64,
+    index: u32,
+    dimension: u64
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: usize, seed: u64) -> Self {
+        let samples = samples_per_pixel.max(1) as u32;
+        let across = (samples as f32).sqrt().round().max(1.0) as u32;
+        let grid = (across, samples.div_ceil(across));
+        Self { samples, grid, global_seed: seed, seed, index: 0, dimension: 0 }
+    }
+
+    /// A fresh hash for the current dimension, from which to choose its strata and jitter.
+    fn next_hash(&mut self) -> u64 {
+        self.dimension += 1;
+        hash_combine(self.seed, self.dimension)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, pixel: (usize, usize), index: usize) {
+        (self.seed, self.index, self.dimension) = (pixel_seed(self.global_seed, pixel), index as u32, 0);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let h = self.next_hash();
+        let stratum = permute(self.index % self.samples, self.samples, h as u32);
+        let jitter = to_unit(hash_combine(h, self.index as u64) as u32);
+        (stratum as f32 + jitter) / self.samples as f32
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        let h = self.next_hash();
+        let (nx, ny) = self.grid;
+        let stratum = permute(self.index % (nx * ny), nx * ny, h as u32);
+        let jitter = hash_combine(h, self.index as u64);
+        (
+            ((stratum % nx) as f32 + to_unit(jitter as u32)) / nx as f32,
+            ((stratum / nx) as f32 + to_unit((jitter >> 32) as u32)) / ny as f32
+        )
+    }
+}
+
+/// The digits of `index` in base `base`, reflected about the decimal point.
+fn radical_inverse(base: u32, mut index: u64) -> f32 {
+    let inv_base = 1.0 / base as f64;
+    let (mut reversed, mut inv_base_n) = (0u64, 1.0);
+    while index > 0 {
+        let next = index / base as u64;
+        reversed = reversed * base as u64 + (index - next * base as u64);
+        inv_base_n *= inv_base;
+        index = next;
+    }
+    ((reversed as f64 * inv_base_n) as f32).min(1.0 - f32::EPSILON)
+}
+
+const PRIMES: [u32; 48] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+    97, 101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191,
+    193, 197, 199, 211, 223
+];
+
+/// Uses a different prime base for each dimension, each shifted (modulo 1) by a per-pixel
+/// random offset. Beyond the first 48 dimensions, where high bases are poorly distributed,
+/// values are simply random.
+pub struct HaltonSampler {
+    global_seed: u64,
+    seed: u64,
+    index: u64,
+    dimension: usize
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self { Self { global_seed: seed, seed, index: 0, dimension: 0 } }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, pixel: (usize, usize), index: usize) {
+        (self.seed, self.index, self.dimension) = (pixel_seed(self.global_seed, pixel), index as u64, 0);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let offset = to_unit(hash_combine(self.seed, self.dimension as u64) as u32);
+        let value = match PRIMES.get(self.dimension) {
+            Some(&base) => (radical_inverse(base, self.index) + offset).fract(),
+            None => to_unit(hash_combine(hash_combine(self.seed, self.dimension as u64), self.index) as u32)
+        };
+        self.dimension += 1;
+        value
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) { (self.get_1d(), self.get_1d()) }
+}
+
+/// Direction numbers for the first four dimensions of the Sobol sequence, generated from the
+/// primitive polynomials of Joe and Kuo.
+fn sobol_directions() -> [[u32; 32]; 4] {
+    let mut v = [[0; 32]; 4];
+    for (i, d) in v[0].iter_mut().enumerate() { *d = 1 << (31 - i); }
+
+    // (degree, coefficients, initial direction numbers) for each further dimension:
+    let polynomials: [(usize, u32, &[u32]); 3] = [(1, 0, &[1]), (2, 1, &[1, 3]), (3, 1, &[1, 3, 1])];
+    for (dim, &(s, a, m)) in polynomials.iter().enumerate() {
+        let v = &mut v[dim + 1];
+        for i in 0..32 {
+            v[i] = if i < s { m[i] << (31 - i) } else {
+                let mut x = v[i - s] ^ (v[i - s] >> s);
+                for k in 1..s { x ^= ((a >> (s - 1 - k)) & 1) * v[i - k]; }
+                x
+            };
+        }
+    }
+    v
+}
+
+/// An Owen scramble of the bits of `x`, driven by `seed` (Laine and Karras' hash, as used by
+/// Burley in "Practical Hash-based Owen Scrambling").
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x.reverse_bits()
+}
+
+/// Generates points of the four-dimensional Sobol sequence, shuffled and Owen-scrambled
+/// differently for each pixel. Longer sequences are padded out with independently scrambled
+/// four-dimensional blocks, and 2D samples always come from a pair of dimensions within one
+/// block, so they keep the sequence's good 2D distribution.
+pub struct SobolSampler {
+    directions: [[u32; 32]; 4],
+    global_seed: u64,
+    seed: u64,
+    index: u32,
+    dimension: usize,
+    block: [f32; 4]
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self { directions: sobol_directions(), global_seed: seed, seed, index: 0, dimension: 0, block: [0.0; 4] }
+    }
+
+    fn sobol(&self, index: u32, dim: usize) -> u32 {
+        (0..32).filter(|bit| (index >> bit) & 1 == 1).fold(0, |x, bit| x ^ self.directions[dim][bit])
+    }
+
+    fn fill_block(&mut self) {
+        let seed = hash_combine(self.seed, (self.dimension / 4) as u64);
+        let index = nested_uniform_scramble(self.index, seed as u32);
+        for dim in 0..4 {
+            let scramble = hash_combine(seed, dim as u64 + 1) as u32;
+            self.block[dim] = to_unit(nested_uniform_scramble(self.sobol(index, dim), scramble));
+        }
+    }
+
+    fn next(&mut self) -> f32 {
+        if self.dimension.is_multiple_of(4) { self.fill_block(); }
+        let value = self.block[self.dimension % 4];
+        self.dimension += 1;
+        value
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, pixel: (usize, usize), index: usize) {
+        (self.seed, self.index, self.dimension) = (pixel_seed(self.global_seed, pixel), index as u32, 0);
+    }
+
+    fn get_1d(&mut self) -> f32 { self.next() }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        if self.dimension % 2 == 1 { self.dimension += 1; }
+        (self.next(), self.next())
+    }
+}
@@ -1,13 +1,90 @@
+use std::io;
+use std::str::FromStr;
 use std::sync::Arc;
 
-use crate::{hittable_list::HittableList, material::*, sphere::Sphere, triangle::Triangle, vec3::{Point3, Colour}, util::random_double, stl::import};
+use crate::{bezier::BezierPatch, camera::{Projection, View}, bvh::BvhNode, patches, heightfield::{HeightMap, Heightfield}, cone::Cone, csg::Csg, cuboid::Cuboid, disk::Disk, curve::{Curve, CurveKind}, hair::HairBsdf, cylinder::Cylinder, paraboloid::Paraboloid, torus::Torus, hittable::{Hittable, Solid}, hittable_list::HittableList, material::*, motion::{Animated, AnimatedTransform, Keyframe, Quaternion}, moving_sphere::MovingSphere, plane::Plane, quad::Quad, sdf::{Sdf, SdfShape}, sphere::Sphere, transform::{Transform, Transformed}, triangle::Triangle, vec3::{Point3, Colour, Vec3, cross, random_unit_vector}, rng::Rng, util::random_double, stl::import, vox, obj, subdivision::PolyMesh, texture::ImageTexture, bump::{BumpMap, NormalMap}, sphere::sphere_uv};
 
 
-pub fn random_scene() -> HittableList {
+/// A world to render, and the view of it that the scene was made for.
+pub struct Scene {
+    pub world: HittableList,
+    pub view: View
+}
+
+/// Builds a scene from its description on the command line: the name of one of the scenes below,
+/// followed by the files or numbers it takes (e.g. `custom_model ../teapot.stl`). Terrain can also
+/// be loaded from raw floats, given the map's width and depth after its path.
+pub fn by_name(description: &str) -> Result<Scene, String> {
+    let words: Vec<&str> = description.split_whitespace().collect();
+    let file = |i: usize| words.get(i).copied().ok_or(format!("Scene '{description}' needs a file path."));
+    let seed = || number(words.get(1), 0);
+
+    let y_up = |look_from: Point3, look_at: Point3| View::new(look_from, look_at, Vec3::new(0.0, 1.0, 0.0));
+    let z_up = |look_from: Point3, look_at: Point3| View::new(look_from, look_at, Vec3::new(0.0, 0.0, 1.0));
+    let spheres_view = y_up(Point3::new(13.0, 2.0, 3.0), Point3::new(0.0, 0.0, 0.0)).with_fov(20.0).with_aperture(0.1, 10.0);
+    let cornell_view = y_up(Point3::new(1.5, 1.0, 2.5), Point3::new(1.5, 1.0, -2.0)).with_fov(45.0);
+    let model_view = z_up(Point3::new(-3.0, -10.0, 8.0), Point3::new(0.0, 0.0, 2.0)).with_aperture(0.1, 10.0);
+
+    let (world, view) = match words.first().copied().unwrap_or("") {
+        "random_scene" => (random_scene(seed()?), spheres_view),
+        "bouncing_spheres" => (bouncing_spheres(seed()?), spheres_view),
+        "basic_scene" => (basic_scene(), y_up(Point3::new(0.0, 0.3, 1.0), Point3::new(0.0, 0.0, -1.0)).with_fov(60.0)),
+        "cornell" => (cornell(), cornell_view),
+        "lit_cornell" => (lit_cornell(), cornell_view),
+        "basic_scene_tri" => (basic_scene_tri(), y_up(Point3::new(0.0, 1.0, 3.0), Point3::new(0.0, 1.0, -1.0)).with_fov(60.0)),
+        "custom_model" => (custom_model(file(1)?)?, model_view),
+        "instanced_model" => (instanced_model(file(1)?)?, z_up(Point3::new(0.0, -20.0, 12.0), Point3::new(0.0, 0.0, 1.0)).with_fov(50.0)),
+        "animated_model" => (animated_model(file(1)?)?, model_view),
+        // Seen square on, as in a technical drawing:
+        "machine_parts" => (
+            machine_parts(),
+            y_up(Point3::new(4.0, 6.0, 9.0), Point3::new(0.0, 0.5, 0.5)).with_projection(Projection::Orthographic).with_fov(40.0)
+        ),
+        "drilled_plate" => (drilled_plate(), y_up(Point3::new(2.0, 5.0, 9.0), Point3::new(0.0, 0.8, 0.0)).with_fov(35.0)),
+        "distance_fields" => (distance_fields(), y_up(Point3::new(0.0, 3.0, 9.0), Point3::new(0.0, 0.7, 0.5)).with_fov(45.0)),
+        "terrain" => {
+            let map = match words.len() {
+                2 => HeightMap::load_image(file(1)?),
+                _ => HeightMap::load_raw(file(1)?, number(words.get(2), 0)?, number(words.get(3), 0)?)
+            };
+            let world = terrain(map.map_err(|e| format!("Couldn't load height map: {e}"))?);
+            (world, y_up(Point3::new(0.0, 12.0, 22.0), Point3::new(0.0, 1.5, 0.0)).with_fov(50.0))
+        }
+        "patch_model" => (patch_model(file(1)?)?, model_view),
+        "hair_and_grass" => (hair_and_grass(seed()?), z_up(Point3::new(0.0, -5.0, 2.5), Point3::new(0.0, 0.0, 0.8)).with_fov(45.0)),
+        "voxel_model" => (voxel_model(file(1)?)?, z_up(Point3::new(6.0, -8.0, 6.0), Point3::new(0.0, 0.0, 2.0)).with_fov(40.0)),
+        "subdivided_model" => (subdivided_model(file(1)?, number(words.get(2), 2)?)?, model_view),
+        "normal_mapped_model" => (normal_mapped_model(file(1)?, file(2)?)?, model_view),
+        "subdivided_cubes" => (subdivided_cubes(), z_up(Point3::new(0.0, -10.0, 6.0), Point3::new(0.0, 0.0, 1.0)).with_fov(40.0)),
+        "surface_detail" => (surface_detail(), z_up(Point3::new(0.0, -8.0, 4.0), Point3::new(0.0, 0.0, 1.0)).with_fov(40.0)),
+        name => return Err(format!("Unknown scene '{name}'."))
+    };
+    Ok(Scene { world, view })
+}
+
+/// Describes a failure to load `file_path`, for `by_name` to report.
+fn load_error(file_path: &str) -> impl FnOnce(io::Error) -> String + '_ {
+    move |e| format!("Couldn't load {file_path}: {e}")
+}
+
+/// Parses a number from a scene description, or gives `default` if it's left out.
+fn number<T: FromStr>(word: Option<&&str>, default: T) -> Result<T, String> {
+    word.map_or(Ok(default), |w| w.parse().map_err(|_| format!("Expected a number in the scene description, not '{w}'.")))
+}
+
+/// The spheres are placed randomly, but the same `seed` always gives the same scene.
+pub fn random_scene(seed: u64) -> HittableList { random_spheres(false, seed) }
+
+/// The `random_scene`, but with the small diffuse spheres bouncing upwards while the shutter is
+/// open, for motion blur.
+pub fn bouncing_spheres(seed: u64) -> HittableList { random_spheres(true, seed) }
+
+fn random_spheres(bouncing: bool, seed: u64) -> HittableList {
     fn sphere(x: f32, y: f32, z: f32, r: f32, m: Arc<dyn Material>) -> Sphere {
         Sphere::new(Point3::new(x, y, z), r, m)
     }
 
+    let mut rng = Rng::new(seed);
     let mut world = HittableList::new(vec![]);
 
     let ground_material = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
@@ -16,18 +93,23 @@ pub fn random_scene() -> HittableList {
     for a in -11..11 { 
         for b in -11..11 {
             let (a, b) = (a as f32, b as f32);
-            let choose_mat = random_double(0.0, 1.0);
-            let centre = Point3::new(a + 0.9 * random_double(0.0, 1.0), 0.2, b + 0.9 * random_double(0.0, 1.0));
+            let choose_mat = random_double(&mut rng, 0.0, 1.0);
+            let centre = Point3::new(a + 0.9 * random_double(&mut rng, 0.0, 1.0), 0.2, b + 0.9 * random_double(&mut rng, 0.0, 1.0));
 
             if (centre - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                 let sphere_material: Arc<dyn Material>;
 
                 if choose_mat < 0.8 {
-                    let albedo = Colour::random(0.0, 1.0) * Colour::random(0.0, 1.0);
+                    let albedo = Colour::random(&mut rng, 0.0, 1.0) * Colour::random(&mut rng, 0.0, 1.0);
                     sphere_material = Arc::new(Lambertian::new(albedo));
+                    if bouncing {
+                        let centre1 = centre + Vec3::new(0.0, random_double(&mut rng, 0.0, 0.5), 0.0);
+                        world.add(Arc::new(MovingSphere::new(centre, centre1, 0.0, 1.0, 0.2, sphere_material)));
+                        continue;
+                    }
                 } else if choose_mat < 0.95 {
-                    let albedo = Colour::random(0.5, 1.0);
-                    let fuzz = random_double(0.0, 0.5);
+                    let albedo = Colour::random(&mut rng, 0.5, 1.0);
+                    let fuzz = random_double(&mut rng, 0.0, 0.5);
                     sphere_material = Arc::new(Metal::new(albedo, fuzz));
                 } else {
                     sphere_material = Arc::new(Dielectric::new(1.5));
@@ -76,95 +158,16 @@ pub fn cornell() -> HittableList {
     const R: f32 = 0.4;
 
     HittableList::new(vec![
-        // Left Wall:
-        Arc::new(
-            Triangle::new(
-                Point3::new( 0.0,  0.0, -1.0),
-                Point3::new( 0.0,  0.0, -3.0),
-                Point3::new( 0.0,  2.0, -1.0),
-                red.clone()
-            )
-        ),
-        Arc::new(
-            Triangle::new(
-                Point3::new( 0.0,  2.0, -1.0),
-                Point3::new( 0.0,  0.0, -3.0),
-                Point3::new( 0.0,  2.0, -3.0),
-                red
-            )
-        ),
-
-        // Right Wall:
-        Arc::new(
-            Triangle::new(
-                Point3::new( 3.0,  0.0, -1.0),
-                Point3::new( 3.0,  2.0, -1.0),
-                Point3::new( 3.0,  0.0, -3.0),
-                green.clone()
-            )
-        ),
-        Arc::new(
-            Triangle::new(
-                Point3::new( 3.0,  2.0, -1.0),
-                Point3::new( 3.0,  2.0, -3.0),
-                Point3::new( 3.0,  0.0, -3.0),
-                green
-            )
-        ),
+        // Walls:
+        Arc::new(Quad::new(Point3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 2.0, 0.0), red)),
+        Arc::new(Quad::new(Point3::new(3.0, 0.0, -1.0), Vec3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 2.0, 0.0), green)),
+        Arc::new(Quad::new(Point3::new(0.0, 0.0, -3.0), Vec3::new(3.0, 0.0,  0.0), Vec3::new(0.0, 2.0, 0.0), white.clone())),
 
         // Ground:
-        Arc::new(
-            Triangle::new(
-                Point3::new( 0.0,  0.0, -1.0),
-                Point3::new( 3.0,  0.0, -1.0),
-                Point3::new( 3.0,  0.0, -3.0),
-                white.clone()
-            )
-        ),
-        Arc::new(
-            Triangle::new(
-                Point3::new( 3.0,  0.0, -3.0),
-                Point3::new( 0.0,  0.0, -3.0),
-                Point3::new( 0.0,  0.0, -1.0),
-                white.clone()
-            )
-        ),
-
-        // Back Wall:
-        Arc::new(
-            Triangle::new(
-                Point3::new( 3.0,  2.0, -3.0),
-                Point3::new( 0.0,  2.0, -3.0),
-                Point3::new( 0.0,  0.0, -3.0),
-                white.clone()
-            )
-        ),
-        Arc::new(
-            Triangle::new(
-                Point3::new( 3.0,  2.0, -3.0),
-                Point3::new( 0.0,  0.0, -3.0),
-                Point3::new( 3.0,  0.0, -3.0),
-                white.clone()
-            )
-        ),
+        Arc::new(Quad::new(Point3::new(0.0, 0.0, -1.0), Vec3::new(3.0, 0.0,  0.0), Vec3::new(0.0, 0.0, -2.0), white.clone())),
 
         // Ceiling:
-        // Arc::new(
-        //     Triangle::new(
-        //         Point3::new( 3.0,  2.0, -3.0),
-        //         Point3::new( 3.0,  2.0, -1.0),
-        //         Point3::new( 0.0,  2.0, -1.0),
-        //         white.clone()
-        //     )
-        // ),
-        // Arc::new(
-        //     Triangle::new(
-        //         Point3::new( 0.0,  2.0, -1.0),
-        //         Point3::new( 0.0,  2.0, -3.0),
-        //         Point3::new( 3.0,  2.0, -3.0),
-        //         white.clone()
-        //     )
-        // ),
+        // Arc::new(Quad::new(Point3::new(0.0, 2.0, -1.0), Vec3::new(3.0, 0.0,  0.0), Vec3::new(0.0, 0.0, -2.0), white.clone())),
 
         // Hollow Glass Sphere:
         Arc::new(Sphere::new(Point3::new(1.5, R, -1.4), R, mat_glass.clone())),
@@ -173,7 +176,18 @@ pub fn cornell() -> HittableList {
         Arc::new(Sphere::new(Point3::new(0.8, R, -2.0), R, mat_metal)),
         Arc::new(Sphere::new(Point3::new(2.2, R, -2.0), R, mat_lamb)),
     ])
+}
 
+/// The `cornell` box with its ceiling in place and a lamp just below it, facing down, which
+/// diffuse surfaces sample directly.
+pub fn lit_cornell() -> HittableList {
+    let white = Arc::new(Lambertian::new(Colour::new(0.8, 0.8, 0.8)));
+    let mat_light = Arc::new(DiffuseLight::new(Colour::new(8.0, 8.0, 8.0)));
+
+    let mut world = cornell();
+    world.add(Arc::new(Quad::new(Point3::new(0.0, 2.0, -1.0), Vec3::new(3.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -2.0), white)));
+    world.add_light(Arc::new(Quad::new(Point3::new(1.0, 1.999, -1.5), Vec3::new(0.0, 0.0, -1.0), Vec3::new(1.0, 0.0, 0.0), mat_light)));
+    world
 }
 
 pub fn basic_scene_tri() -> HittableList {
@@ -223,35 +237,374 @@ pub fn basic_scene_tri() -> HittableList {
     ])
 }
 
-pub fn custom_model(file_path: &str) -> HittableList {
-    let raw_tris = import(file_path).unwrap();
-
+pub fn custom_model(file_path: &str) -> Result<HittableList, String> {
     let mat_model = Arc::new(Metal::new(Colour::new(0.8, 0.2, 0.2), 0.01)); 
     let mat_ground = Arc::new(Metal::new(Colour::new(0.6, 0.6, 0.6), 0.05));
 
+    Ok(HittableList::new(vec![
+        Arc::new(mesh(file_path, mat_model)?),
+        // Add a ground plane:
+        Arc::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), mat_ground)),
+    ]))
+}
+
+/// Loads an STL file into a BVH of triangles, all sharing one material.
+pub fn mesh(file_path: &str, material: Arc<dyn Material>) -> Result<BvhNode, String> {
+    let tris = import(file_path).map_err(load_error(file_path))?.into_iter()
+        .map(|tri| Arc::new(Triangle::new(tri[0], tri[1], tri[2], material.clone())) as Arc<dyn Hittable>)
+        .collect();
+    Ok(BvhNode::new(tris))
+}
+
+/// A ring of copies of one model, each rotated and scaled differently, which all share a single
+/// mesh and BVH.
+pub fn instanced_model(file_path: &str) -> Result<HittableList, String> {
+    let mat_model = Arc::new(Metal::new(Colour::new(0.8, 0.2, 0.2), 0.01));
+    let mat_ground = Arc::new(Lambertian::new(Colour::new(0.6, 0.6, 0.6)));
+    let model = Arc::new(mesh(file_path, mat_model)?);
+
     let mut world = HittableList::new(vec![]);
-    raw_tris.into_iter().for_each(|tri| world.add(
-        Arc::new(Triangle::new(tri[0], tri[1], tri[2], mat_model.clone()))
-    ));
-
-    // Add a ground plane:
-    world.add(
-        Arc::new(Triangle::new(
-            Point3::new( -100000.0,  10000.000000, 0.0),
-            Point3::new( -100000.0, -10000.000000, 0.0),
-            Point3::new(  100000.0, -10000.000000, 0.0),
-            mat_ground.clone()
-        ))
+    const COUNT: usize = 6;
+    for i in 0..COUNT {
+        let angle = 360.0 * i as f32 / COUNT as f32;
+        let size = 0.5 + 0.1 * i as f32;
+        let placement = Transform::scale(Vec3::new(size, size, size))
+            .then(&Transform::rotate(Vec3::new(0.0, 0.0, 1.0), angle))
+            .then(&Transform::translate(Vec3::new(8.0, 0.0, 0.0)))
+            .then(&Transform::rotate(Vec3::new(0.0, 0.0, 1.0), angle));
+        world.add(Arc::new(Transformed::new(model.clone(), placement)));
+    }
+
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1000.0), 1000.0, mat_ground)));
+    Ok(world)
+}
+
+/// A model spinning about its vertical axis while sliding across the ground, for motion blur.
+/// The animation runs from time 0 to 1, so should be paired with a camera shutter in that range.
+pub fn animated_model(file_path: &str) -> Result<HittableList, String> {
+    let mat_model = Arc::new(Metal::new(Colour::new(0.8, 0.2, 0.2), 0.01));
+    let mat_ground = Arc::new(Lambertian::new(Colour::new(0.6, 0.6, 0.6)));
+
+    let up = Vec3::new(0.0, 0.0, 1.0);
+    let unit = Vec3::new(1.0, 1.0, 1.0);
+    let animation = AnimatedTransform::linear(
+        Keyframe::new(0.0, Vec3::new(-2.0, 0.0, 0.0), Quaternion::from_axis_angle(up,  0.0), unit),
+        Keyframe::new(1.0, Vec3::new( 2.0, 0.0, 0.0), Quaternion::from_axis_angle(up, 90.0), unit)
     );
-    
-    world.add(
-        Arc::new(Triangle::new(
-            Point3::new( -100000.0,  10000.000000, 0.0),
-            Point3::new(  100000.0, -10000.000000, 0.0),
-            Point3::new(  100000.0,  10000.000000, 0.0),
-            mat_ground.clone()
-        ))
+
+    Ok(HittableList::new(vec![
+        Arc::new(Animated::new(mesh(file_path, mat_model)?, animation)),
+        Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1000.0), 1000.0, mat_ground)),
+    ]))
+}
+
+/// A selection of the exact curved primitives, standing on a plane with a turned block and a
+/// brass disc.
+pub fn machine_parts() -> HittableList {
+    let mat_ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    let mat_steel = Arc::new(Metal::new(Colour::new(0.7, 0.7, 0.75), 0.15));
+    let mat_brass = Arc::new(Metal::new(Colour::new(0.8, 0.6, 0.2), 0.05));
+    let mat_paint = Arc::new(Lambertian::new(Colour::new(0.1, 0.3, 0.7)));
+    let mat_glass = Arc::new(Dielectric::new(1.5));
+
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    HittableList::new(vec![
+        Arc::new(Plane::new(Point3::new(0.0, 0.0, 0.0), up, mat_ground)),
+        Arc::new(Cylinder::new(Point3::new(-3.0, 0.0, 0.0), Point3::new(-3.0, 2.0, 0.0), 0.6, mat_steel.clone())),
+        Arc::new(Cone::new(Point3::new(-1.0, 0.0, 0.0), Point3::new(-1.0, 2.0, 0.0), 0.7, mat_paint.clone())),
+        Arc::new(Torus::new(Point3::new(1.0, 0.3, 0.0), up, 0.7, 0.3, mat_brass.clone())),
+        Arc::new(Paraboloid::new(Point3::new(3.0, 0.0, 0.0), Point3::new(3.0, 1.5, 0.0), 0.8, mat_glass)),
+        Arc::new(Cylinder::new(Point3::new(0.0, 0.4, 2.0), Point3::new(1.5, 0.4, 1.5), 0.4, mat_steel)),
+        Arc::new(Cuboid::oriented(Point3::new(-2.5, 0.3, 2.0), Vec3::new(1.2, 0.6, 0.8), &Transform::rotate(up, 30.0), mat_paint)),
+        Arc::new(Disk::new(Point3::new(2.5, 0.001, 2.0), up, 0.5, mat_brass)),
+    ])
+}
+
+/// A steel plate with a grid of holes drilled through it, and a lens made by intersecting two
+/// spheres, all built with CSG.
+pub fn drilled_plate() -> HittableList {
+    let mat_ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    let mat_steel = Arc::new(Metal::new(Colour::new(0.7, 0.7, 0.75), 0.2));
+    let mat_glass = Arc::new(Dielectric::new(1.5));
+
+    let blank = Arc::new(Cuboid::new(Point3::new(-2.0, 0.0, -1.5), Point3::new(2.0, 0.3, 1.5), mat_steel.clone()));
+    let holes = (0..12)
+        .map(|k| {
+            let (x, z) = (-1.5 + (k % 4) as f32, -1.0 + (k / 4) as f32);
+            Arc::new(Cylinder::new(Point3::new(x, -1.0, z), Point3::new(x, 1.0, z), 0.25, mat_steel.clone())) as Arc<dyn Solid>
+        })
+        .reduce(|a, b| Arc::new(Csg::union(a, b)))
+        .unwrap();
+    let plate = Csg::difference(blank, holes);
+
+    let lens = Csg::intersection(
+        Arc::new(Sphere::new(Point3::new(0.0, 1.2, -1.6), 2.0, mat_glass.clone())),
+        Arc::new(Sphere::new(Point3::new(0.0, 1.2,  1.6), 2.0, mat_glass))
     );
-    
-    world
-}
\ No newline at end of file
+
+    HittableList::new(vec![
+        Arc::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), mat_ground)),
+        Arc::new(Transformed::new(plate, Transform::translate(Vec3::new(0.0, 0.01, 0.0)))),
+        // The lens's axis (along z) tipped 30 degrees towards x:
+        Arc::new(Transformed::new(lens, Transform::look_at(Point3::new(0.0, 0.8, 0.0), Point3::new(1.0, 0.8, 3.0_f32.sqrt()), Vec3::new(0.0, 1.0, 0.0)))),
+    ])
+}
+
+/// Procedural shapes built from signed distance functions: a blob of blended spheres, a twisted
+/// rounded bar, a row of rings, a Mandelbulb, and a rounded cube with a cross cut through it.
+pub fn distance_fields() -> HittableList {
+    let mat_ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    let mat_blob = Arc::new(Lambertian::new(Colour::new(0.8, 0.3, 0.2)));
+    let mat_bar = Arc::new(Metal::new(Colour::new(0.7, 0.7, 0.75), 0.1));
+    let mat_rings = Arc::new(Metal::new(Colour::new(0.8, 0.6, 0.2), 0.05));
+    let mat_fractal = Arc::new(Lambertian::new(Colour::new(0.2, 0.4, 0.8)));
+
+    let blob = Sdf::sphere(0.6)
+        .smooth_union(Sdf::sphere(0.4).translate(Vec3::new(0.6, 0.4, 0.0)), 0.3)
+        .smooth_union(Sdf::sphere(0.35).translate(Vec3::new(-0.5, 0.5, 0.2)), 0.3)
+        .smooth_subtract(Sdf::sphere(0.3).translate(Vec3::new(0.0, 0.2, 0.6)), 0.1)
+        .translate(Vec3::new(-3.0, 0.6, 0.0));
+    let bar = Sdf::rounded_cuboid(Vec3::new(0.3, 1.0, 0.3), 0.08)
+        .twist(1.5)
+        .translate(Vec3::new(-1.0, 1.0, 0.0));
+    let rings = Sdf::torus(0.3, 0.08)
+        .repeat(Vec3::new(0.0, 0.0, 0.8), Vec3::new(0.0, 0.0, 2.0))
+        .translate(Vec3::new(1.0, 0.08, 0.0));
+    let fractal = Sdf::mandelbulb(8.0, 10).scale(0.8).translate(Vec3::new(3.0, 1.0, 0.0));
+    let cross = Sdf::cuboid(Vec3::new(1.0, 0.2, 0.2))
+        .union(Sdf::cuboid(Vec3::new(0.2, 1.0, 0.2)))
+        .union(Sdf::cuboid(Vec3::new(0.2, 0.2, 1.0)));
+    let cut_cube = Sdf::cuboid(Vec3::new(0.5, 0.5, 0.5))
+        .intersection(Sdf::sphere(0.68))
+        .subtract(cross)
+        .translate(Vec3::new(0.0, 0.5, 2.0));
+
+    HittableList::new(vec![
+        Arc::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), mat_ground)),
+        Arc::new(SdfShape::new(blob, mat_blob)),
+        Arc::new(SdfShape::new(bar, mat_bar)),
+        Arc::new(SdfShape::new(rings, mat_rings)),
+        Arc::new(SdfShape::new(fractal, mat_fractal.clone()).with_limits(512, 1e-3)),
+        Arc::new(SdfShape::new(cut_cube, mat_fractal)),
+    ])
+}
+
+/// Terrain from a height map, 20 units across and up to 3 high, centred on the origin, with a
+/// glass sphere floating above it.
+pub fn terrain(map: HeightMap) -> HittableList {
+    let mat_ground = Arc::new(Lambertian::new(Colour::new(0.4, 0.5, 0.3)));
+    let mat_glass = Arc::new(Dielectric::new(1.5));
+
+    let land = Heightfield::new(map, Vec3::new(20.0, 3.0, 20.0), mat_ground);
+
+    HittableList::new(vec![
+        Arc::new(Transformed::new(land, Transform::translate(Vec3::new(-10.0, 0.0, -10.0)))),
+        Arc::new(Sphere::new(Point3::new(0.0, 5.0, 0.0), 1.5, mat_glass)),
+    ])
+}
+
+/// Like `custom_model`, but for a model made of bicubic patches (such as the original Utah
+/// teapot), which renders smoothly rather than faceted.
+pub fn patch_model(file_path: &str) -> Result<HittableList, String> {
+    let mat_model = Arc::new(Metal::new(Colour::new(0.8, 0.2, 0.2), 0.01));
+    let mat_ground = Arc::new(Metal::new(Colour::new(0.6, 0.6, 0.6), 0.05));
+
+    let patches = patches::import(file_path).map_err(load_error(file_path))?.into_iter()
+        .map(|cp| Arc::new(BezierPatch::new(cp, 3, mat_model.clone())) as Arc<dyn Hittable>)
+        .collect();
+
+    Ok(HittableList::new(vec![
+        Arc::new(BvhNode::new(patches)),
+        Arc::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), mat_ground)),
+    ]))
+}
+
+/// A furry ball on a lawn, made of a few thousand hair and grass curves.
+pub fn hair_and_grass(seed: u64) -> HittableList {
+    let mut rng = Rng::new(seed);
+    let mat_hair = Arc::new(HairBsdf::from_melanin(1.3, 0.4, 0.3, 0.3));
+    let mat_skin = Arc::new(Lambertian::new(Colour::new(0.3, 0.2, 0.15)));
+    let mat_grass = Arc::new(Lambertian::new(Colour::new(0.2, 0.5, 0.1)));
+    let mat_ground = Arc::new(Lambertian::new(Colour::new(0.3, 0.25, 0.2)));
+
+    let centre = Point3::new(0.0, 0.0, 1.0);
+    let mut curves: Vec<Arc<dyn Hittable>> = vec![];
+
+    // Strands grow out from the ball and droop under their own weight:
+    for _ in 0..3000 {
+        let dir = random_unit_vector(&mut rng);
+        let root = centre + 0.98 * dir;
+        let length = random_double(&mut rng, 0.4, 0.6);
+        let droop = Vec3::new(0.0, 0.0, -0.4 * length);
+        let cp = [
+            root,
+            root + length / 3.0 * dir,
+            root + 2.0 * length / 3.0 * dir + 0.5 * droop,
+            root + length * dir + droop,
+        ];
+        curves.push(Arc::new(Curve::new(cp, (0.01, 0.002), CurveKind::Cylinder, mat_hair.clone())));
+    }
+
+    // Blades of grass, leaning in random directions:
+    for _ in 0..3000 {
+        let root = Point3::new(random_double(&mut rng, -4.0, 4.0), random_double(&mut rng, -4.0, 4.0), 0.0);
+        if (root - Point3::new(0.0, 0.0, 0.0)).length() < 0.8 { continue }
+        let height = random_double(&mut rng, 0.2, 0.5);
+        let lean = random_double(&mut rng, 0.1, 0.3) * height * Vec3::new(random_double(&mut rng, -1.0, 1.0), random_double(&mut rng, -1.0, 1.0), 0.0);
+        let cp = [
+            root,
+            root + Vec3::new(0.0, 0.0, height / 2.0),
+            root + Vec3::new(0.0, 0.0, height) + 0.5 * lean,
+            root + Vec3::new(0.0, 0.0, height) + 2.0 * lean,
+        ];
+        let facing = cross(&Vec3::new(0.0, 0.0, 1.0), &lean);
+        curves.push(Arc::new(Curve::new(cp, (0.04, 0.0), CurveKind::Ribbon { n0: facing, n1: facing }, mat_grass.clone())));
+    }
+
+    HittableList::new(vec![
+        Arc::new(BvhNode::new(curves)),
+        Arc::new(Sphere::new(centre, 1.0, mat_skin)),
+        Arc::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), mat_ground)),
+    ])
+}
+
+/// The first model in a MagicaVoxel file, standing on a plane and scaled to about 4 units tall.
+pub fn voxel_model(file_path: &str) -> Result<HittableList, String> {
+    let mat_ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+
+    let file = vox::import(file_path).map_err(load_error(file_path))?;
+    let grid = file.grid(0);
+    let [w, d, h] = grid.size();
+    let scale = 4.0 / w.max(d).max(h) as f32;
+    let place = Transform::translate(Vec3::new(-0.5 * w as f32, -0.5 * d as f32, 0.0))
+        .then(&Transform::scale(Vec3::new(scale, scale, scale)));
+
+    Ok(HittableList::new(vec![
+        Arc::new(Transformed::new(grid, place)),
+        Arc::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), mat_ground)),
+    ]))
+}
+
+/// Triangles (from `PolyMesh::triangles`) gathered into a BVH.
+pub fn triangle_mesh(triangles: &[[Point3; 3]], material: Arc<dyn Material>) -> BvhNode {
+    let tris = triangles.iter()
+        .map(|tri| Arc::new(Triangle::new(tri[0], tri[1], tri[2], material.clone())) as Arc<dyn Hittable>)
+        .collect();
+    BvhNode::new(tris)
+}
+
+/// An OBJ or STL model, smoothed with `levels` rounds of subdivision. Edges where faces meet at
+/// more than 60 degrees are kept sharp.
+pub fn subdivided_model(file_path: &str, levels: usize) -> Result<HittableList, String> {
+    let mat_model = Arc::new(Metal::new(Colour::new(0.8, 0.2, 0.2), 0.01));
+    let mat_ground = Arc::new(Metal::new(Colour::new(0.6, 0.6, 0.6), 0.05));
+
+    let mut model = if file_path.to_lowercase().ends_with(".obj") {
+        obj::import(file_path).map_err(load_error(file_path))?
+    } else {
+        PolyMesh::from_triangles(&import(file_path).map_err(load_error(file_path))?)
+    };
+    model.mark_creases_by_angle(60.0);
+
+    Ok(HittableList::new(vec![
+        Arc::new(triangle_mesh(&model.subdivide(levels).triangles(), mat_model)),
+        Arc::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), mat_ground)),
+    ]))
+}
+
+/// An OBJ model with a tangent-space normal map applied through its texture coordinates. Faces
+/// without texture coordinates are left unmapped.
+pub fn normal_mapped_model(file_path: &str, normal_map_path: &str) -> Result<HittableList, String> {
+    let mat_ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    let normal_map = ImageTexture::load(normal_map_path).map_err(load_error(normal_map_path))?;
+    let mat_model = Arc::new(NormalMap::new(Arc::new(Lambertian::new(Colour::new(0.7, 0.6, 0.5))), normal_map));
+
+    let tris = obj::read(file_path).map_err(load_error(file_path))?.triangles().into_iter()
+        .map(|(tri, uvs)| {
+            let triangle = Triangle::new(tri[0], tri[1], tri[2], mat_model.clone());
+            Arc::new(match uvs { Some(uvs) => triangle.with_uvs(uvs), None => triangle }) as Arc<dyn Hittable>
+        })
+        .collect();
+
+    Ok(HittableList::new(vec![
+        Arc::new(BvhNode::new(tris)),
+        Arc::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), mat_ground)),
+    ]))
+}
+
+/// Cubes subdivided in different ways: plain Catmull-Clark (which rounds the cube off), with the
+/// top edges creased, and as triangles with Loop subdivision.
+pub fn subdivided_cubes() -> HittableList {
+    let mat_ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    let mat_cube = Arc::new(Lambertian::new(Colour::new(0.7, 0.3, 0.2)));
+
+    let positions = (0..8).map(|i| Point3::new((i & 1) as f32, ((i >> 1) & 1) as f32, (i >> 2) as f32)).collect::<Vec<_>>();
+    let quads = vec![
+        vec![0, 2, 3, 1], vec![4, 5, 7, 6], vec![0, 1, 5, 4],
+        vec![2, 6, 7, 3], vec![0, 4, 6, 2], vec![1, 3, 7, 5],
+    ];
+    let cube = PolyMesh::new(positions.clone(), quads.clone());
+
+    let mut creased = cube.clone();
+    for (a, b) in [(4, 5), (5, 7), (7, 6), (6, 4)] { creased.mark_crease(a, b); }
+
+    let triangles = quads.iter().flat_map(|q| [vec![q[0], q[1], q[2]], vec![q[0], q[2], q[3]]]).collect();
+    let triangulated = PolyMesh::new(positions, triangles);
+
+    let models = [cube.catmull_clark(4), creased.catmull_clark(4), triangulated.loop_subdivide(4)];
+    let mut objects: Vec<Arc<dyn Hittable>> = models.iter().enumerate().map(|(i, m)| {
+        let place = Transform::scale(Vec3::new(2.0, 2.0, 2.0)).then(&Transform::translate(Vec3::new(3.0 * i as f32 - 4.0, -1.0, 0.0)));
+        Arc::new(Transformed::new(triangle_mesh(&m.triangles(), mat_cube.clone()), place)) as Arc<dyn Hittable>
+    }).collect();
+    objects.push(Arc::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), mat_ground)));
+    HittableList::new(objects)
+}
+
+/// Spheres given surface detail in different ways: with a normal map, a bump map, and by
+/// displacing a finely subdivided mesh, on a bump-mapped tiled floor. The maps are generated,
+/// rather than loaded.
+pub fn surface_detail() -> HittableList {
+    let grey = |h: f32| Colour::new(h, h, h);
+    let mat_red = Arc::new(Lambertian::new(Colour::new(0.7, 0.2, 0.2)));
+    let mat_gold = Arc::new(Metal::new(Colour::new(0.8, 0.6, 0.2), 0.1));
+    let mat_floor = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+
+    // Raised tiles, one per unit of texture coordinate:
+    let tiles = ImageTexture::from_fn(128, 128, |u, v| {
+        let edge = (u - 0.5).abs().max((v - 0.5).abs());
+        grey(((0.5 - edge) * 20.0).min(1.0))
+    });
+    let dimples = ImageTexture::from_fn(512, 256, |u, v| {
+        let (x, y) = ((u * 32.0).fract() - 0.5, (v * 16.0).fract() - 0.5);
+        grey(1.0 - (-(x * x + y * y) * 20.0).exp())
+    });
+    let ridges = ImageTexture::from_fn(512, 256, |u, v| grey(0.5 + 0.5 * (u * 60.0 * std::f32::consts::PI + 8.0 * v).sin()));
+
+    let normal_mapped = Arc::new(NormalMap::new(mat_red.clone(), ridges.to_normal_map(4.0)));
+    let bump_mapped = Arc::new(BumpMap::new(mat_gold, dimples, 0.004));
+    let floor = Arc::new(BumpMap::new(mat_floor, tiles, 0.02));
+
+    // An octahedron, subdivided and pushed out into a sphere, then displaced with a wave:
+    let octahedron = PolyMesh::new(
+        vec![
+            Point3::new(1.0, 0.0, 0.0), Point3::new(-1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0),
+            Point3::new(0.0, -1.0, 0.0), Point3::new(0.0, 0.0, 1.0), Point3::new(0.0, 0.0, -1.0),
+        ],
+        vec![
+            vec![0, 2, 4], vec![2, 1, 4], vec![1, 3, 4], vec![3, 0, 4],
+            vec![2, 0, 5], vec![1, 2, 5], vec![3, 1, 5], vec![0, 3, 5],
+        ]
+    );
+    let sphere = octahedron.loop_subdivide(6).displace(|p, _| 1.0 - p.length());
+    let displaced = sphere.displace(|_, n| {
+        let (u, v) = sphere_uv(n);
+        0.08 * (u * 12.0 * std::f32::consts::PI).sin() * (v * std::f32::consts::PI).sin()
+    });
+
+    HittableList::new(vec![
+        Arc::new(Sphere::new(Point3::new(-2.5, 0.0, 1.0), 1.0, normal_mapped)),
+        Arc::new(Sphere::new(Point3::new(0.0, 0.0, 1.0), 1.0, bump_mapped)),
+        Arc::new(Transformed::new(triangle_mesh(&displaced.triangles(), mat_red), Transform::translate(Vec3::new(2.5, 0.0, 1.0)))),
+        Arc::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), floor)),
+    ])
+}
@@ -0,0 +1,261 @@
+use std::sync::Arc;
+
+use crate::aabb::{Aabb, overlapping_box, surrounding_box};
+use crate::hittable::*;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::*;
+
+/// A tree of signed distance functions: negative inside the shape, positive outside.
+///
+/// Primitives are centred on the origin; use `translate` etc. to move them.
+#[derive(Clone, Debug)]
+pub enum Sdf {
+    Sphere { radius: f32 },
+    Cuboid { half_extents: Vec3 },
+    RoundedCuboid { half_extents: Vec3, radius: f32 },
+    /// A ring in the xz-plane, around the y-axis.
+    Torus { major_radius: f32, minor_radius: f32 },
+    Mandelbulb { power: f32, iterations: usize },
+
+    Translate { offset: Vec3, inner: Box<Sdf> },
+    Scale { factor: f32, inner: Box<Sdf> },
+    /// Rotates each slice of `inner` about the y-axis by `rate` radians per unit of height.
+    Twist { rate: f32, inner: Box<Sdf> },
+    /// Copies of `inner` every `spacing` units, with up to `limit` copies either side of the
+    /// original along each axis.
+    Repeat { spacing: Vec3, limit: Vec3, inner: Box<Sdf> },
+
+    Union(Box<Sdf>, Box<Sdf>),
+    Intersection(Box<Sdf>, Box<Sdf>),
+    Subtract(Box<Sdf>, Box<Sdf>),
+    /// A union which blends the two shapes together over a distance of `k`.
+    SmoothUnion { a: Box<Sdf>, b: Box<Sdf>, k: f32 },
+    SmoothSubtract { a: Box<Sdf>, b: Box<Sdf>, k: f32 },
+}
+
+fn abs(v: Vec3) -> Vec3 { Vec3::new(v.x().abs(), v.y().abs(), v.z().abs()) }
+fn max0(v: Vec3) -> Vec3 { Vec3::new(v.x().max(0.0), v.y().max(0.0), v.z().max(0.0)) }
+fn mix(a: f32, b: f32, h: f32) -> f32 { a + h * (b - a) }
+
+impl Sdf {
+    pub fn sphere(radius: f32) -> Self { Sdf::Sphere { radius } }
+    pub fn cuboid(half_extents: Vec3) -> Self { Sdf::Cuboid { half_extents } }
+    pub fn rounded_cuboid(half_extents: Vec3, radius: f32) -> Self { Sdf::RoundedCuboid { half_extents, radius } }
+    pub fn torus(major_radius: f32, minor_radius: f32) -> Self { Sdf::Torus { major_radius, minor_radius } }
+    pub fn mandelbulb(power: f32, iterations: usize) -> Self { Sdf::Mandelbulb { power, iterations } }
+
+    pub fn translate(self, offset: Vec3) -> Self { Sdf::Translate { offset, inner: Box::new(self) } }
+    pub fn scale(self, factor: f32) -> Self { Sdf::Scale { factor, inner: Box::new(self) } }
+    pub fn twist(self, rate: f32) -> Self { Sdf::Twist { rate, inner: Box::new(self) } }
+    pub fn repeat(self, spacing: Vec3, limit: Vec3) -> Self { Sdf::Repeat { spacing, limit, inner: Box::new(self) } }
+
+    pub fn union(self, other: Sdf) -> Self { Sdf::Union(Box::new(self), Box::new(other)) }
+    pub fn intersection(self, other: Sdf) -> Self { Sdf::Intersection(Box::new(self), Box::new(other)) }
+    pub fn subtract(self, other: Sdf) -> Self { Sdf::Subtract(Box::new(self), Box::new(other)) }
+    pub fn smooth_union(self, other: Sdf, k: f32) -> Self { Sdf::SmoothUnion { a: Box::new(self), b: Box::new(other), k } }
+    pub fn smooth_subtract(self, other: Sdf, k: f32) -> Self { Sdf::SmoothSubtract { a: Box::new(self), b: Box::new(other), k } }
+
+    pub fn distance(&self, p: &Point3) -> f32 {
+        match self {
+            Sdf::Sphere { radius } => p.length() - radius,
+            Sdf::Cuboid { half_extents } => {
+                let q = abs(*p) - *half_extents;
+                max0(q).length() + q.x().max(q.y()).max(q.z()).min(0.0)
+            }
+            Sdf::RoundedCuboid { half_extents, radius } => {
+                let r = Vec3::new(*radius, *radius, *radius);
+                Sdf::Cuboid { half_extents: *half_extents - r }.distance(p) - radius
+            }
+            Sdf::Torus { major_radius, minor_radius } => {
+                let ring = (p.x() * p.x() + p.z() * p.z()).sqrt() - major_radius;
+                (ring * ring + p.y() * p.y()).sqrt() - minor_radius
+            }
+            Sdf::Mandelbulb { power, iterations } => mandelbulb(p, *power, *iterations),
+
+            Sdf::Translate { offset, inner } => inner.distance(&(*p - *offset)),
+            Sdf::Scale { factor, inner } => factor * inner.distance(&(*p / *factor)),
+            Sdf::Twist { rate, inner } => {
+                let (s, c) = (rate * p.y()).sin_cos();
+                inner.distance(&Point3::new(c * p.x() - s * p.z(), p.y(), s * p.x() + c * p.z()))
+            }
+            Sdf::Repeat { spacing, limit, inner } => {
+                let mut q = *p;
+                for a in 0..3 {
+                    if spacing[a] > 0.0 {
+                        q[a] -= spacing[a] * (p[a] / spacing[a]).round().clamp(-limit[a], limit[a]);
+                    }
+                }
+                inner.distance(&q)
+            }
+
+            Sdf::Union(a, b) => a.distance(p).min(b.distance(p)),
+            Sdf::Intersection(a, b) => a.distance(p).max(b.distance(p)),
+            Sdf::Subtract(a, b) => a.distance(p).max(-b.distance(p)),
+            Sdf::SmoothUnion { a, b, k } => {
+                let (d1, d2) = (a.distance(p), b.distance(p));
+                let h = (0.5 + 0.5 * (d2 - d1) / k).clamp(0.0, 1.0);
+                mix(d2, d1, h) - k * h * (1.0 - h)
+            }
+            Sdf::SmoothSubtract { a, b, k } => {
+                let (d1, d2) = (a.distance(p), b.distance(p));
+                let h = (0.5 - 0.5 * (d1 + d2) / k).clamp(0.0, 1.0);
+                mix(d1, -d2, h) + k * h * (1.0 - h)
+            }
+        }
+    }
+
+    /// A box which the shape never extends outside of, or `None` if it is unbounded.
+    pub fn bounds(&self) -> Option<Aabb> {
+        let symmetric = |e: Vec3| Some(Aabb::new(-e, e));
+        match self {
+            Sdf::Sphere { radius } => symmetric(Vec3::new(*radius, *radius, *radius)),
+            Sdf::Cuboid { half_extents } | Sdf::RoundedCuboid { half_extents, .. } => symmetric(*half_extents),
+            Sdf::Torus { major_radius, minor_radius } => {
+                let r = major_radius + minor_radius;
+                symmetric(Vec3::new(r, *minor_radius, r))
+            }
+            // The whole set lies within a radius of about 1.2 for the usual powers.
+            Sdf::Mandelbulb { .. } => symmetric(Vec3::new(1.25, 1.25, 1.25)),
+
+            Sdf::Translate { offset, inner } => inner.bounds().map(|b| Aabb::new(b.min() + *offset, b.max() + *offset)),
+            Sdf::Scale { factor, inner } => inner.bounds().map(|b| Aabb::from_points(&[*factor * b.min(), *factor * b.max()])),
+            Sdf::Twist { inner, .. } => inner.bounds().map(|b| {
+                let r = xz_radius(&b);
+                Aabb::new(Point3::new(-r, b.min().y(), -r), Point3::new(r, b.max().y(), r))
+            }),
+            Sdf::Repeat { spacing, limit, inner } => inner.bounds().map(|b| {
+                let reach = *spacing * *limit;
+                Aabb::new(b.min() - reach, b.max() + reach)
+            }),
+
+            Sdf::Union(a, b) => Some(surrounding_box(&a.bounds()?, &b.bounds()?)),
+            Sdf::Intersection(a, b) => match (a.bounds(), b.bounds()) {
+                (Some(x), Some(y)) => overlapping_box(&x, &y).or(Some(x)),
+                (x, y) => x.or(y),
+            },
+            Sdf::Subtract(a, _) | Sdf::SmoothSubtract { a, .. } => a.bounds(),
+            Sdf::SmoothUnion { a, b, k } => {
+                let both = surrounding_box(&a.bounds()?, &b.bounds()?);
+                let pad = Vec3::new(*k, *k, *k);
+                Some(Aabb::new(both.min() - pad, both.max() + pad))
+            }
+        }
+    }
+
+    /// An upper bound on how fast the function changes with distance. Exact distance fields have
+    /// a value of 1; larger values mean the ray marcher has to take proportionally smaller steps.
+    pub fn lipschitz(&self) -> f32 {
+        match self {
+            Sdf::Twist { rate, inner } => {
+                let r = inner.bounds().map_or(1.0, |b| xz_radius(&b));
+                inner.lipschitz() * (1.0 + (rate * r) * (rate * r)).sqrt()
+            }
+            Sdf::Translate { inner, .. } | Sdf::Scale { inner, .. } | Sdf::Repeat { inner, .. } => inner.lipschitz(),
+            Sdf::Union(a, b) | Sdf::Intersection(a, b) | Sdf::Subtract(a, b)
+            | Sdf::SmoothUnion { a, b, .. } | Sdf::SmoothSubtract { a, b, .. } => a.lipschitz().max(b.lipschitz()),
+            _ => 1.0,
+        }
+    }
+}
+
+/// The largest distance from the y-axis of any point in `b`.
+fn xz_radius(b: &Aabb) -> f32 {
+    let x = b.min().x().abs().max(b.max().x().abs());
+    let z = b.min().z().abs().max(b.max().z().abs());
+    (x * x + z * z).sqrt()
+}
+
+/// The distance estimator for the Mandelbulb fractal.
+fn mandelbulb(p: &Point3, power: f32, iterations: usize) -> f32 {
+    let mut z = *p;
+    let mut dr = 1.0;
+    let mut r = 0.0;
+
+    for _ in 0..iterations {
+        r = z.length();
+        if r > 2.0 { break }
+
+        // Convert to polar coordinates, then scale and rotate the point:
+        let theta = (z.z() / r).acos() * power;
+        let phi = z.y().atan2(z.x()) * power;
+        dr = r.powf(power - 1.0) * power * dr + 1.0;
+
+        let zr = r.powf(power);
+        z = zr * Vec3::new(theta.sin() * phi.cos(), phi.sin() * theta.sin(), theta.cos()) + *p;
+    }
+    0.5 * r.ln() * r / dr
+}
+
+/// A shape defined by an `Sdf`, rendered by sphere tracing.
+pub struct SdfShape {
+    sdf: Sdf,
+    bounds: Option<Aabb>,
+    step_scale: f32,
+    max_steps: usize,
+    epsilon: f32,
+    material: Arc<dyn Material>
+}
+
+impl SdfShape {
+    pub fn new(sdf: Sdf, material: Arc<dyn Material>) -> Self {
+        let bounds = sdf.bounds();
+        let step_scale = 1.0 / sdf.lipschitz();
+        Self { sdf, bounds, step_scale, max_steps: 256, epsilon: 1e-4, material }
+    }
+
+    /// Sets the number of steps after which a ray is assumed to miss, and how close it must get
+    /// to the surface to count as a hit.
+    pub fn with_limits(mut self, max_steps: usize, epsilon: f32) -> Self {
+        self.max_steps = max_steps;
+        self.epsilon = epsilon;
+        self
+    }
+
+    fn normal(&self, p: &Point3) -> Vec3 {
+        let h = self.epsilon;
+        let d = |dx: f32, dy: f32, dz: f32| self.sdf.distance(&(*p + Vec3::new(dx, dy, dz)));
+        unit_vector(&Vec3::new(
+            d(h, 0.0, 0.0) - d(-h, 0.0, 0.0),
+            d(0.0, h, 0.0) - d(0.0, -h, 0.0),
+            d(0.0, 0.0, h) - d(0.0, 0.0, -h)
+        ))
+    }
+}
+
+impl Hittable for SdfShape {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (mut t, t_end) = match &self.bounding_box() {
+            Some(b) => b.interval(r, t_min, t_max)?,
+            None => (t_min, t_max.min(1e6)),
+        };
+        let speed = r.direction().length();
+
+        // Using the absolute distance lets rays which start inside the shape find their way out.
+        // A ray scattered off the surface starts within `epsilon` of it, so a hit only counts once
+        // the ray has got clear of the surface it started on. A march starting on the bounds
+        // rather than the ray's origin can't be on the surface:
+        let mut left_surface = t > t_min;
+        for _ in 0..self.max_steps {
+            let d = self.sdf.distance(&r.at(t)).abs();
+            if d >= self.epsilon { left_surface = true; }
+            else if left_surface {
+                let p = r.at(t);
+                let mut rec = HitRecord::new(p, Vec3::default(), self.material.clone(), t, true);
+                rec.set_face_normal(r, &self.normal(&p));
+                return Some(rec)
+            }
+            t += (d * self.step_scale).max(0.5 * self.epsilon) / speed;
+            if t > t_end { return None }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // Padded, so that marches clipped to the box start clear of any surface on its faces.
+        let pad = Vec3::new(1.0, 1.0, 1.0) * 4.0 * self.epsilon;
+        self.bounds.map(|b| Aabb::new(b.min() - pad, b.max() + pad))
+    }
+}
+
+impl Solid for SdfShape {}
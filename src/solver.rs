@@ -0,0 +1,120 @@
+// Polynomial root finders, used for intersecting rays with curved surfaces.
+//
+// The cubic and quartic solvers follow Jochen Schwarze's "Cubic and Quartic Roots" from
+// Graphics Gems I, working in f64 since the quartic in particular loses precision quickly.
+
+const EPS: f64 = 1e-9;
+
+fn is_zero(x: f64) -> bool { x.abs() < EPS }
+
+/// Returns the real roots of `a*x^2 + b*x + c`, in ascending order. If `a` is (close to) zero
+/// the equation is treated as linear, with a single root.
+pub fn solve_quadratic(a: f32, b: f32, c: f32) -> Vec<f32> {
+    if a.abs() < 1e-12 {
+        return if b.abs() < 1e-12 { vec![] } else { vec![-c / b] }
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 { return vec![] }
+
+    // Avoids cancellation when `b` is close to `sqrt(discriminant)`:
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (x0, x1) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+    vec![x0.min(x1), x0.max(x1)]
+}
+
+/// Real roots of `c[2]*x^2 + c[1]*x + c[0]`.
+fn solve_quadric(c: [f64; 3]) -> Vec<f64> {
+    let p = c[1] / (2.0 * c[2]);
+    let q = c[0] / c[2];
+    let d = p * p - q;
+
+    if is_zero(d) { vec![-p] }
+    else if d < 0.0 { vec![] }
+    else {
+        let sqrt_d = d.sqrt();
+        vec![sqrt_d - p, -sqrt_d - p]
+    }
+}
+
+/// Real roots of `c[3]*x^3 + c[2]*x^2 + c[1]*x + c[0]`.
+pub fn solve_cubic(c: [f64; 4]) -> Vec<f64> {
+    // Normal form: x^3 + Ax^2 + Bx + C = 0
+    let a = c[2] / c[3];
+    let b = c[1] / c[3];
+    let c = c[0] / c[3];
+
+    // Substitute x = y - A/3 to eliminate the quadric term: x^3 + px + q = 0
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c) / 2.0;
+
+    // Using Cardano's formula:
+    let cb_p = p * p * p;
+    let d = q * q + cb_p;
+
+    let mut s = if is_zero(d) {
+        if is_zero(q) { vec![0.0] } // One triple solution.
+        else {
+            let u = (-q).cbrt(); // One single and one double solution.
+            vec![2.0 * u, -u]
+        }
+    } else if d < 0.0 {
+        // Casus irreducibilis: three real solutions.
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        let third = std::f64::consts::PI / 3.0;
+        vec![t * phi.cos(), -t * (phi + third).cos(), -t * (phi - third).cos()]
+    } else {
+        // One real solution.
+        let sqrt_d = d.sqrt();
+        vec![(sqrt_d - q).cbrt() - (sqrt_d + q).cbrt()]
+    };
+
+    s.iter_mut().for_each(|x| *x -= a / 3.0);
+    s
+}
+
+/// Real roots of `c[4]*x^4 + c[3]*x^3 + c[2]*x^2 + c[1]*x + c[0]`, each refined with a step of
+/// Newton's method.
+pub fn solve_quartic(coeffs: [f64; 5]) -> Vec<f64> {
+    // Normal form: x^4 + Ax^3 + Bx^2 + Cx + D = 0
+    let a = coeffs[3] / coeffs[4];
+    let b = coeffs[2] / coeffs[4];
+    let c = coeffs[1] / coeffs[4];
+    let d = coeffs[0] / coeffs[4];
+
+    // Substitute x = y - A/4 to eliminate the cubic term: x^4 + px^2 + qx + r = 0
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = sq_a * a / 8.0 - a * b / 2.0 + c;
+    let r = -3.0 / 256.0 * sq_a * sq_a + sq_a * b / 16.0 - a * c / 4.0 + d;
+
+    let mut s = if is_zero(r) {
+        // No absolute term: y(y^3 + py + q) = 0
+        let mut s = solve_cubic([q, p, 0.0, 1.0]);
+        s.push(0.0);
+        s
+    } else {
+        // Solve the resolvent cubic, and use one of its roots to factor into two quadrics:
+        let z = solve_cubic([r * p / 2.0 - q * q / 8.0, -r, -p / 2.0, 1.0])[0];
+
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        let u = if is_zero(u) { 0.0 } else if u > 0.0 { u.sqrt() } else { return vec![] };
+        let v = if is_zero(v) { 0.0 } else if v > 0.0 { v.sqrt() } else { return vec![] };
+
+        let mut s = solve_quadric([z - u, if q < 0.0 { -v } else { v }, 1.0]);
+        s.extend(solve_quadric([z + u, if q < 0.0 { v } else { -v }, 1.0]));
+        s
+    };
+
+    let eval = |x: f64| (((coeffs[4] * x + coeffs[3]) * x + coeffs[2]) * x + coeffs[1]) * x + coeffs[0];
+    let deriv = |x: f64| ((4.0 * coeffs[4] * x + 3.0 * coeffs[3]) * x + 2.0 * coeffs[2]) * x + coeffs[1];
+    s.iter_mut().for_each(|x| {
+        *x -= a / 4.0;
+        let slope = deriv(*x);
+        if slope.abs() > EPS { *x -= eval(*x) / slope; }
+    });
+    s
+}
@@ -1,5 +1,7 @@
+use std::f32::consts::PI;
 use std::sync::Arc;
 
+use crate::aabb::Aabb;
 use crate::hittable::*;
 use crate::material::Material;
 use crate::vec3::*;
@@ -20,27 +22,49 @@ impl Sphere {
 
 impl Hittable for Sphere {
     fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let oc = r.origin() - self.centre;
-        let a = r.direction().length_squared();
-        let half_b = dot(&oc, &r.direction());
-        let c = oc.length_squared() - self.radius * self.radius;
+        hit_sphere(self.centre, self.radius, &self.material, r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::from_points(&[self.centre - r, self.centre + r]))
+    }
+}
+
+impl Solid for Sphere {}
+
+/// Intersects `r` with a sphere; shared by `Sphere` and `MovingSphere`.
+pub fn hit_sphere(centre: Point3, radius: f32, material: &Arc<dyn Material>, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    let oc = r.origin() - centre;
+    let a = r.direction().length_squared();
+    let half_b = dot(&oc, &r.direction());
+    let c = oc.length_squared() - radius * radius;
 
-        let discriminant = half_b * half_b - a * c;
-        if discriminant < 0.0 { return None }
-        let sqrtd = discriminant.sqrt();
+    let discriminant = half_b * half_b - a * c;
+    if discriminant < 0.0 { return None }
+    let sqrtd = discriminant.sqrt();
 
-        // Find the nearest root that lies in the acceptable range.
-        let mut root = (-half_b - sqrtd) / a;
+    // Find the nearest root that lies in the acceptable range.
+    let mut root = (-half_b - sqrtd) / a;
+    if root < t_min || t_max < root {
+        root = (-half_b + sqrtd) / a;
         if root < t_min || t_max < root {
-            root = (-half_b + sqrtd) / a;
-            if root < t_min || t_max < root {
-                return None
-            }
+            return None
         }
-
-        let mut hr = HitRecord::new(r.at(root), Vec3::default(), self.material.clone(), root, bool::default());
-        let outward_normal = (hr.p - self.centre) / self.radius;
-        hr.set_face_normal(r, &outward_normal);
-        Some(hr)
     }
-}
\ No newline at end of file
+
+    let mut hr = HitRecord::new(r.at(root), Vec3::default(), material.clone(), root, bool::default());
+    let outward_normal = (hr.p - centre) / radius;
+    hr.set_face_normal(r, &outward_normal);
+    (hr.u, hr.v) = sphere_uv(&outward_normal);
+    hr.tangent = Vec3::new(outward_normal.z(), 0.0, -outward_normal.x());
+    Some(hr)
+}
+
+/// Maps a point `p` on the unit sphere to (u, v), where `u` is the angle around the y-axis
+/// (from x = -1) and `v` the angle from y = -1 to y = +1, both scaled to [0, 1].
+pub fn sphere_uv(p: &Point3) -> (f32, f32) {
+    let theta = (-p.y()).clamp(-1.0, 1.0).acos();
+    let phi = (-p.z()).atan2(p.x()) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
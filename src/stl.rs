@@ -6,22 +6,25 @@ use crate::vec3::Point3;
 /// 
 /// This is a very simple and naive reader, which can only read ASCII encoded files.
 pub fn import(fp: &str) -> io::Result<Vec<[Point3; 3]>> {
-    Ok(get_triangles(&fs::read_to_string(fp)?))
+    get_triangles(&fs::read_to_string(fp)?)
 }
 
-fn get_triangles(text: &str) -> Vec<[Point3; 3]> {
+fn invalid(msg: &str) -> io::Error { io::Error::new(io::ErrorKind::InvalidData, msg.to_string()) }
+
+fn get_triangles(text: &str) -> io::Result<Vec<[Point3; 3]>> {
     let lines: Vec<_> = text.lines().skip(1).collect();
     lines.chunks_exact(7).map(|chunk| {
-        let points: Vec<_> = chunk[2..=4].iter().map(to_vertex).collect();
-        [points[0], points[1], points[2]]
+        let points = chunk[2..=4].iter().map(to_vertex).collect::<io::Result<Vec<_>>>()?;
+        Ok([points[0], points[1], points[2]])
     }).collect()
 }
 
-fn to_vertex(line: &&str) -> Point3 {
-    assert!(line.starts_with("vertex"));
+fn to_vertex(line: &&str) -> io::Result<Point3> {
+    if !line.starts_with("vertex") { return Err(invalid("Expected a vertex.")) }
     let ns: Vec<f32> = line.split_whitespace().skip(1)
-        .map(|n| n.parse::<f32>().unwrap())
-        .collect();
-    
-    Point3::new(ns[0], ns[1], ns[2])
-}
\ No newline at end of file
+        .map(|n| n.parse::<f32>().map_err(|_| invalid("Could not parse vertex.")))
+        .collect::<io::Result<_>>()?;
+    if ns.len() < 3 { return Err(invalid("Could not parse vertex.")) }
+
+    Ok(Point3::new(ns[0], ns[1], ns[2]))
+}
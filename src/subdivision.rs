@@ -0,0 +1,276 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::vec3::*;
+
+type Edge = (usize, usize);
+
+fn edge(a: usize, b: usize) -> Edge { (a.min(b), a.max(b)) }
+
+/// A polygon mesh with shared vertices, which can be smoothed by subdivision before being split
+/// into triangles for rendering.
+///
+/// Edges on the boundary of the mesh, edges shared by more than two faces, and edges marked as
+/// creases all stay sharp: points on them only move along the edge, and vertices where three or
+/// more of them meet stay fixed.
+#[derive(Clone, Debug, Default)]
+pub struct PolyMesh {
+    positions: Vec<Point3>,
+    faces: Vec<Vec<usize>>,
+    creases: HashSet<Edge>
+}
+
+/// The faces around each edge, and the edges around each vertex, in a fixed order so that
+/// subdividing the same mesh always gives the same result.
+struct Topology {
+    edges: Vec<Edge>,
+    edge_index: HashMap<Edge, usize>,
+    edge_faces: Vec<Vec<usize>>,
+    vertex_edges: Vec<Vec<usize>>,
+    vertex_faces: Vec<Vec<usize>>
+}
+
+impl Topology {
+    fn new(mesh: &PolyMesh) -> Self {
+        let mut topo = Self {
+            edges: vec![],
+            edge_index: HashMap::new(),
+            edge_faces: vec![],
+            vertex_edges: vec![vec![]; mesh.positions.len()],
+            vertex_faces: vec![vec![]; mesh.positions.len()]
+        };
+        for (f, face) in mesh.faces.iter().enumerate() {
+            for (i, &a) in face.iter().enumerate() {
+                let b = face[(i + 1) % face.len()];
+                let e = edge(a, b);
+                let index = *topo.edge_index.entry(e).or_insert_with(|| {
+                    topo.edges.push(e);
+                    topo.edge_faces.push(vec![]);
+                    topo.vertex_edges[e.0].push(topo.edges.len() - 1);
+                    topo.vertex_edges[e.1].push(topo.edges.len() - 1);
+                    topo.edges.len() - 1
+                });
+                topo.edge_faces[index].push(f);
+                topo.vertex_faces[a].push(f);
+            }
+        }
+        topo
+    }
+
+    fn is_sharp(&self, mesh: &PolyMesh, e: usize) -> bool {
+        self.edge_faces[e].len() != 2 || mesh.creases.contains(&self.edges[e])
+    }
+
+    /// The neighbours of `v` along sharp edges.
+    fn sharp_neighbours(&self, mesh: &PolyMesh, v: usize) -> Vec<usize> {
+        self.vertex_edges[v].iter()
+            .filter(|&&e| self.is_sharp(mesh, e))
+            .map(|&e| if self.edges[e].0 == v { self.edges[e].1 } else { self.edges[e].0 })
+            .collect()
+    }
+}
+
+impl PolyMesh {
+    /// Faces list their vertices counter-clockwise, as seen from the front.
+    pub fn new(positions: Vec<Point3>, faces: Vec<Vec<usize>>) -> Self {
+        assert!(faces.iter().flatten().all(|&i| i < positions.len()), "Face refers to a missing vertex.");
+        assert!(faces.iter().all(|f| f.len() >= 3), "Faces need at least three vertices.");
+        // Faces which use a vertex twice (as zero-area triangles in an STL file do, once their
+        // corners are joined up) have no area to subdivide, and are dropped:
+        let faces = faces.into_iter().filter(|f| f.iter().enumerate().all(|(i, v)| !f[..i].contains(v))).collect();
+        Self { positions, faces, creases: HashSet::new() }
+    }
+
+    /// Builds a mesh from separate triangles (as read from an STL file), joining up vertices
+    /// which have exactly the same position.
+    pub fn from_triangles(triangles: &[[Point3; 3]]) -> Self {
+        let mut positions = vec![];
+        let mut index = HashMap::new();
+        let faces = triangles.iter().map(|tri| {
+            tri.iter().map(|p| *index.entry([p.x(), p.y(), p.z()].map(f32::to_bits)).or_insert_with(|| {
+                positions.push(*p);
+                positions.len() - 1
+            })).collect()
+        }).collect();
+        Self::new(positions, faces)
+    }
+
+    pub fn is_triangle_mesh(&self) -> bool { self.faces.iter().all(|f| f.len() == 3) }
+
+    /// Marks the edge between vertices `a` and `b` as a sharp crease.
+    pub fn mark_crease(&mut self, a: usize, b: usize) { self.creases.insert(edge(a, b)); }
+
+    /// Marks every edge where the faces on either side meet at more than `degrees` as a crease.
+    pub fn mark_creases_by_angle(&mut self, degrees: f32) {
+        let topo = Topology::new(self);
+        let normals: Vec<Vec3> = self.faces.iter().map(|f| self.face_normal(f)).collect();
+        let threshold = degrees.to_radians().cos();
+        for (e, faces) in topo.edge_faces.iter().enumerate() {
+            if let [f0, f1] = faces[..] {
+                if dot(&normals[f0], &normals[f1]) < threshold { self.creases.insert(topo.edges[e]); }
+            }
+        }
+    }
+
+    /// The normal of a face, with length twice its area. Newell's method copes with polygons
+    /// that aren't quite planar.
+    fn face_area_normal(&self, face: &[usize]) -> Vec3 {
+        face.iter().enumerate().fold(Vec3::default(), |n, (i, &a)| {
+            n + cross(&self.positions[a], &self.positions[face[(i + 1) % face.len()]])
+        })
+    }
+
+    fn face_normal(&self, face: &[usize]) -> Vec3 {
+        let n = self.face_area_normal(face);
+        if n.length_squared() > 0.0 { unit_vector(&n) } else { n }
+    }
+
+    /// Applies `levels` rounds of Loop subdivision if every face is a triangle, or Catmull-Clark
+    /// subdivision otherwise.
+    pub fn subdivide(&self, levels: usize) -> Self {
+        if self.is_triangle_mesh() { self.loop_subdivide(levels) } else { self.catmull_clark(levels) }
+    }
+
+    /// Applies `levels` rounds of Loop subdivision, each of which splits every triangle into
+    /// four. Panics if the mesh has any faces which aren't triangles.
+    pub fn loop_subdivide(&self, levels: usize) -> Self {
+        assert!(self.is_triangle_mesh(), "Loop subdivision needs a triangle mesh.");
+        let mut mesh = self.clone();
+        for _ in 0..levels { mesh = mesh.loop_step(); }
+        mesh
+    }
+
+    /// Applies `levels` rounds of Catmull-Clark subdivision. After the first round, every face is
+    /// a quad.
+    pub fn catmull_clark(&self, levels: usize) -> Self {
+        let mut mesh = self.clone();
+        for _ in 0..levels { mesh = mesh.catmull_clark_step(); }
+        mesh
+    }
+
+    /// The new position of each original vertex, using `smooth` for those not on a crease.
+    fn move_vertices(&self, topo: &Topology, smooth: impl Fn(usize) -> Point3) -> Vec<Point3> {
+        (0..self.positions.len()).map(|v| {
+            let p = self.positions[v];
+            let sharp = topo.sharp_neighbours(self, v);
+            match sharp[..] {
+                [a, b] => 0.75 * p + 0.125 * (self.positions[a] + self.positions[b]),
+                _ if sharp.len() > 2 || topo.vertex_edges[v].is_empty() => p,
+                _ => smooth(v)
+            }
+        }).collect()
+    }
+
+    /// The crease edges of the subdivided mesh: both halves of each edge that was sharp before.
+    fn split_creases(&self, topo: &Topology, first_edge_point: usize) -> HashSet<Edge> {
+        (0..topo.edges.len())
+            .filter(|&e| self.creases.contains(&topo.edges[e]))
+            .flat_map(|e| {
+                let (a, b) = topo.edges[e];
+                [edge(a, first_edge_point + e), edge(b, first_edge_point + e)]
+            })
+            .collect()
+    }
+
+    fn loop_step(&self) -> Self {
+        let topo = Topology::new(self);
+        let n = self.positions.len();
+
+        let mut positions = self.move_vertices(&topo, |v| {
+            let neighbours: Vec<usize> = topo.vertex_edges[v].iter()
+                .map(|&e| if topo.edges[e].0 == v { topo.edges[e].1 } else { topo.edges[e].0 })
+                .collect();
+            let k = neighbours.len() as f32;
+            let beta = if neighbours.len() == 3 { 3.0 / 16.0 } else { 3.0 / (8.0 * k) };
+            let sum = neighbours.iter().fold(Point3::default(), |s, &i| s + self.positions[i]);
+            (1.0 - k * beta) * self.positions[v] + beta * sum
+        });
+
+        // A new point on each edge, weighted towards the vertices opposite it:
+        for (e, &(a, b)) in topo.edges.iter().enumerate() {
+            let (pa, pb) = (self.positions[a], self.positions[b]);
+            positions.push(if topo.is_sharp(self, e) { 0.5 * (pa + pb) } else {
+                let opposite = topo.edge_faces[e].iter().fold(Point3::default(), |s, &f| {
+                    let c = self.faces[f].iter().copied().find(|&i| i != a && i != b).unwrap();
+                    s + self.positions[c]
+                });
+                0.375 * (pa + pb) + 0.125 * opposite
+            });
+        }
+
+        let mid = |a: usize, b: usize| n + topo.edge_index[&edge(a, b)];
+        let faces = self.faces.iter().flat_map(|f| {
+            let (a, b, c) = (f[0], f[1], f[2]);
+            let (ab, bc, ca) = (mid(a, b), mid(b, c), mid(c, a));
+            [vec![a, ab, ca], vec![ab, b, bc], vec![ca, bc, c], vec![ab, bc, ca]]
+        }).collect();
+
+        Self { positions, faces, creases: self.split_creases(&topo, n) }
+    }
+
+    fn catmull_clark_step(&self) -> Self {
+        let topo = Topology::new(self);
+        let (n, m) = (self.positions.len(), topo.edges.len());
+
+        let face_points: Vec<Point3> = self.faces.iter()
+            .map(|f| f.iter().fold(Point3::default(), |s, &i| s + self.positions[i]) / f.len() as f32)
+            .collect();
+        let edge_points: Vec<Point3> = topo.edges.iter().enumerate().map(|(e, &(a, b))| {
+            let mid = 0.5 * (self.positions[a] + self.positions[b]);
+            if topo.is_sharp(self, e) { mid } else {
+                let faces = &topo.edge_faces[e];
+                0.5 * mid + 0.5 * faces.iter().fold(Point3::default(), |s, &f| s + face_points[f]) / faces.len() as f32
+            }
+        }).collect();
+
+        let mut positions = self.move_vertices(&topo, |v| {
+            let p = self.positions[v];
+            let faces = &topo.vertex_faces[v];
+            let edges = &topo.vertex_edges[v];
+            let k = edges.len() as f32;
+            let f = faces.iter().fold(Point3::default(), |s, &i| s + face_points[i]) / faces.len() as f32;
+            let r = edges.iter().fold(Point3::default(), |s, &e| {
+                let (a, b) = topo.edges[e];
+                s + 0.5 * (self.positions[a] + self.positions[b])
+            }) / k;
+            (f + 2.0 * r + (k - 3.0) * p) / k
+        });
+        positions.extend(edge_points);
+        positions.extend(face_points);
+
+        let mid = |a: usize, b: usize| n + topo.edge_index[&edge(a, b)];
+        let faces = self.faces.iter().enumerate().flat_map(|(fi, f)| {
+            let k = f.len();
+            (0..k).map(move |i| vec![f[i], mid(f[i], f[(i + 1) % k]), n + m + fi, mid(f[(i + k - 1) % k], f[i])])
+        }).collect();
+
+        Self { positions, faces, creases: self.split_creases(&topo, n) }
+    }
+
+    /// The normal at each vertex: the average of the surrounding faces' normals, weighted by
+    /// their areas.
+    pub fn vertex_normals(&self) -> Vec<Vec3> {
+        let mut normals = vec![Vec3::default(); self.positions.len()];
+        for face in &self.faces {
+            let n = self.face_area_normal(face);
+            for &i in face { normals[i] += n; }
+        }
+        normals.into_iter().map(|n| if n.length_squared() > 0.0 { unit_vector(&n) } else { n }).collect()
+    }
+
+    /// Moves each vertex along its normal by `height(position, normal)`. Detail finer than the
+    /// mesh's edges is lost, so this is usually applied after subdividing.
+    pub fn displace(&self, height: impl Fn(&Point3, &Vec3) -> f32) -> Self {
+        let positions = self.positions.iter().zip(self.vertex_normals())
+            .map(|(p, n)| *p + height(p, &n) * n)
+            .collect();
+        Self { positions, faces: self.faces.clone(), creases: self.creases.clone() }
+    }
+
+    /// Splits every face into triangles, fanning out from its first vertex.
+    pub fn triangles(&self) -> Vec<[Point3; 3]> {
+        self.faces.iter()
+            .flat_map(|f| (1..f.len() - 1).map(move |i| [f[0], f[i], f[i + 1]]))
+            .map(|tri| tri.map(|i| self.positions[i]))
+            .collect()
+    }
+}
@@ -0,0 +1,103 @@
+use std::{fs, io};
+
+use crate::bitmap::{self, Bitmap};
+use crate::vec3::{Colour, Vec3, unit_vector};
+
+/// An image which can be sampled by texture coordinates, with colours from 0 to 1.
+#[derive(Clone, Debug)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Colour> // Row by row, from the top.
+}
+
+impl ImageTexture {
+    pub fn new(width: usize, height: usize, pixels: Vec<Colour>) -> Self {
+        assert!(width > 0 && height > 0, "An image needs at least one pixel.");
+        assert_eq!(pixels.len(), width * height);
+        Self { width, height, pixels }
+    }
+
+    /// Builds an image from a function of the texture coordinates at each pixel's centre.
+    pub fn from_fn(width: usize, height: usize, f: impl Fn(f32, f32) -> Colour) -> Self {
+        let pixels = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| f((x as f32 + 0.5) / width as f32, 1.0 - (y as f32 + 0.5) / height as f32))
+            .collect();
+        Self::new(width, height, pixels)
+    }
+
+    /// Loads a BMP or PNG image (by extension). Values are used as stored, without any gamma
+    /// correction, as suits normal and height maps.
+    pub fn load(fp: &str) -> io::Result<Self> {
+        if fp.to_lowercase().ends_with(".png") {
+            Self::load_png(fp)
+        } else {
+            Ok(Self::from_bitmap(&Bitmap::read(fs::File::open(fp)?)?))
+        }
+    }
+
+    pub fn from_bitmap(bmp: &Bitmap) -> Self {
+        let pixels = (0..bmp.height())
+            .flat_map(|y| (0..bmp.width()).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let [r, g, b] = bmp.pixel(x, y);
+                Colour::new(r as f32, g as f32, b as f32) / 255.0
+            })
+            .collect();
+        Self::new(bmp.width(), bmp.height(), pixels)
+    }
+
+    pub fn load_png(fp: &str) -> io::Result<Self> {
+        let (width, height, pixels) = bitmap::read_png(fp)?;
+        Ok(Self::new(width, height, pixels))
+    }
+
+    pub fn width(&self) -> usize { self.width }
+    pub fn height(&self) -> usize { self.height }
+
+    fn texel(&self, x: i64, y: i64) -> Colour {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    /// Bilinearly filtered colour at `(u, v)`, with `v = 0` at the bottom of the image. The
+    /// image repeats outside of [0, 1].
+    pub fn sample(&self, u: f32, v: f32) -> Colour {
+        let x = u * self.width as f32 - 0.5;
+        let y = (1.0 - v) * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        (1.0 - fy) * ((1.0 - fx) * self.texel(x0, y0) + fx * self.texel(x0 + 1, y0))
+            + fy * ((1.0 - fx) * self.texel(x0, y0 + 1) + fx * self.texel(x0 + 1, y0 + 1))
+    }
+
+    /// The average of the three channels at `(u, v)`, for height maps.
+    pub fn value(&self, u: f32, v: f32) -> f32 {
+        let c = self.sample(u, v);
+        (c.x() + c.y() + c.z()) / 3.0
+    }
+}
+
+impl ImageTexture {
+    /// Converts a height map into a tangent-space normal map, with `strength` scaling the slopes
+    /// (as height per pixel).
+    pub fn to_normal_map(&self, strength: f32) -> Self {
+        let height = |x: i64, y: i64| {
+            let c = self.texel(x, y);
+            (c.x() + c.y() + c.z()) / 3.0
+        };
+        let pixels = (0..self.height as i64).flat_map(|y| (0..self.width as i64).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                // Rows run downwards, but `v` (and the normal map's green channel) upwards:
+                let dx = strength * 0.5 * (height(x + 1, y) - height(x - 1, y));
+                let dy = strength * 0.5 * (height(x, y - 1) - height(x, y + 1));
+                let n = unit_vector(&Vec3::new(-dx, -dy, 1.0));
+                0.5 * (n + Vec3::new(1.0, 1.0, 1.0))
+            })
+            .collect();
+        Self::new(self.width, self.height, pixels)
+    }
+}
@@ -0,0 +1,108 @@
+use std::str::FromStr;
+
+/// A rectangle of pixels rendered as one unit of work, from `(x0, y0)` up to but not including
+/// `(x1, y1)`, with rows counted from the top of the image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub x0: usize, pub y0: usize,
+    pub x1: usize, pub y1: usize
+}
+
+impl Tile {
+    pub fn width(&self) -> usize { self.x1 - self.x0 }
+    pub fn height(&self) -> usize { self.y1 - self.y0 }
+    pub fn area(&self) -> usize { self.width() * self.height() }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x0..self.x1).contains(&x) && (self.y0..self.y1).contains(&y)
+    }
+}
+
+/// The order in which tiles are handed out to be rendered, which can be parsed from its name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileOrder {
+    /// Row by row from the top-left.
+    Scanline,
+    /// Outwards from the centre of the image, where the subject usually is.
+    Spiral,
+    /// Along a Hilbert curve, so that consecutive tiles are always adjacent.
+    Hilbert
+}
+
+impl FromStr for TileOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scanline" => Ok(TileOrder::Scanline),
+            "spiral" => Ok(TileOrder::Spiral),
+            "hilbert" => Ok(TileOrder::Hilbert),
+            _ => Err(format!("Unknown tile order '{s}'; expected scanline, spiral or hilbert."))
+        }
+    }
+}
+
+/// The `(x, y)` position of the `d`th point along a Hilbert curve filling an `n` by `n` grid,
+/// where `n` is a power of two.
+fn hilbert_point(n: usize, mut d: usize) -> (usize, usize) {
+    let (mut x, mut y) = (0, 0);
+    let mut s = 1;
+    while s < n {
+        let rx = 1 & (d / 2);
+        let ry = 1 & (d ^ rx);
+        if ry == 0 {
+            if rx == 1 { (x, y) = (s - 1 - x, s - 1 - y); }
+            (x, y) = (y, x);
+        }
+        x += s * rx;
+        y += s * ry;
+        d /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+/// The grid positions of `nx` by `ny` tiles, spiralling outwards from the centre.
+fn spiral_points(nx: usize, ny: usize) -> Vec<(usize, usize)> {
+    let mut points = Vec::with_capacity(nx * ny);
+    let (mut x, mut y) = (((nx - 1) / 2) as isize, ((ny - 1) / 2) as isize);
+    let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let (mut direction, mut run) = (0, 1);
+
+    // Walk runs of 1, 1, 2, 2, 3, 3... turning after each, and keep the steps inside the grid:
+    while points.len() < nx * ny {
+        for _ in 0..2 {
+            let (dx, dy) = directions[direction];
+            for _ in 0..run {
+                if (0..nx as isize).contains(&x) && (0..ny as isize).contains(&y) {
+                    points.push((x as usize, y as usize));
+                }
+                (x, y) = (x + dx, y + dy);
+            }
+            direction = (direction + 1) % 4;
+        }
+        run += 1;
+    }
+    points
+}
+
+/// Divides `region` of an image into tiles of (at most) `size` pixels square.
+pub fn tiles(region: &Tile, size: usize, order: TileOrder) -> Vec<Tile> {
+    let size = size.max(1);
+    let (nx, ny) = (region.width().div_ceil(size), region.height().div_ceil(size));
+    if nx == 0 || ny == 0 { return vec![] }
+
+    let points: Vec<_> = match order {
+        TileOrder::Scanline => (0..ny).flat_map(|y| (0..nx).map(move |x| (x, y))).collect(),
+        TileOrder::Spiral => spiral_points(nx, ny),
+        TileOrder::Hilbert => {
+            let n = nx.max(ny).next_power_of_two();
+            (0..n * n).map(|d| hilbert_point(n, d)).filter(|&(x, y)| x < nx && y < ny).collect()
+        }
+    };
+
+    points.into_iter().map(|(x, y)| Tile {
+        x0: region.x0 + x * size, y0: region.y0 + y * size,
+        x1: (region.x0 + (x + 1) * size).min(region.x1), y1: (region.y0 + (y + 1) * size).min(region.y1)
+    }).collect()
+}
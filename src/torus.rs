@@ -0,0 +1,81 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::quadric::*;
+use crate::ray::Ray;
+use crate::solver::solve_quartic;
+use crate::vec3::*;
+
+/// A ring-shaped torus around `axis`, whose tube (of `minor_radius`) sweeps a circle of
+/// `major_radius` about `centre`.
+pub struct Torus {
+    frame: Frame,
+    major_radius: f32,
+    minor_radius: f32,
+    material: Arc<dyn Material>
+}
+
+impl Torus {
+    pub fn new(centre: Point3, axis: Vec3, major_radius: f32, minor_radius: f32, material: Arc<dyn Material>) -> Self {
+        Self { frame: Frame::new(centre, axis), major_radius, minor_radius, material }
+    }
+
+    fn local_box(&self) -> Aabb {
+        let (big, small) = (self.major_radius + self.minor_radius, self.minor_radius);
+        Aabb::new(Point3::new(-big, -big, -small), Point3::new(big, big, small))
+    }
+
+    /// Every intersection of the (local) ray with the torus' surface, in no particular order.
+    pub fn local_hits(&self, o: &Point3, d: &Vec3) -> Vec<LocalHit> {
+        // The quartic's coefficients span many orders of magnitude when the ray starts far away,
+        // so it is solved from a point close to the torus instead:
+        let dd = d.length_squared();
+        let t_shift = (-dot(o, d) / dd - (self.major_radius + self.minor_radius) / dd.sqrt()).max(0.0);
+        let o_near = *o + t_shift * *d;
+
+        // (|p|^2 + R^2 - r^2)^2 = 4R^2(x^2 + y^2), with p = o + td:
+        let (ox, oy, oz) = (o_near.x() as f64, o_near.y() as f64, o_near.z() as f64);
+        let (dx, dy, dz) = (d.x() as f64, d.y() as f64, d.z() as f64);
+        let (big_r2, small_r2) = ((self.major_radius as f64).powi(2), (self.minor_radius as f64).powi(2));
+
+        let dd = dx * dx + dy * dy + dz * dz;
+        let od = ox * dx + oy * dy + oz * dz;
+        let k = ox * ox + oy * oy + oz * oz - small_r2 - big_r2;
+        let coeffs = [
+            k * k - 4.0 * big_r2 * (small_r2 - oz * oz),
+            4.0 * od * k + 8.0 * big_r2 * oz * dz,
+            2.0 * dd * k + 4.0 * od * od + 4.0 * big_r2 * dz * dz,
+            4.0 * dd * od,
+            dd * dd,
+        ];
+
+        solve_quartic(coeffs).into_iter().map(|t| {
+            let t = t as f32 + t_shift;
+            let p = *o + t * *d;
+
+            // The normal points away from the nearest point on the tube's central circle:
+            let ring = self.major_radius * unit_vector(&Vec3::new(p.x(), p.y(), 0.0));
+            let normal = unit_vector(&(p - ring));
+            let around_tube = p.z().atan2(Vec3::new(p.x(), p.y(), 0.0).length() - self.major_radius);
+            LocalHit::new(t, normal, azimuth(&p), (around_tube + PI) / (2.0 * PI))
+        }).collect()
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (o, d) = self.frame.ray_to_local(r);
+        let bounds = self.local_box();
+        if !bounds.hit(&Ray::new(o, d, r.time()), t_min, t_max) { return None }
+        closest_hit(&self.frame, &self.local_hits(&o, &d), &self.material, r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.frame.bounding_box(&self.local_box()))
+    }
+}
+
+impl Solid for Torus {}
@@ -0,0 +1,257 @@
+use std::ops;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::ray::Ray;
+use crate::util::degrees_to_radians;
+use crate::vec3::*;
+
+/// A row-major 4x4 matrix, acting on column vectors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix4([[f32; 4]; 4]);
+
+impl Default for Matrix4 {
+    fn default() -> Self { Self::identity() }
+}
+
+impl Matrix4 {
+    pub fn new(m: [[f32; 4]; 4]) -> Self { Self(m) }
+
+    pub fn identity() -> Self {
+        Self([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut t = [[0.0; 4]; 4];
+        for (i, row) in t.iter_mut().enumerate() {
+            for (j, x) in row.iter_mut().enumerate() { *x = self.0[j][i]; }
+        }
+        Self(t)
+    }
+
+    /// Computes the inverse using Gauss-Jordan elimination with partial pivoting.
+    /// Returns `None` if the matrix is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.0;
+        let mut inv = Self::identity().0;
+
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 { return None }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let p = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= p;
+                inv[col][j] *= p;
+            }
+
+            for row in 0..4 {
+                if row == col { continue }
+                let f = a[row][col];
+                for j in 0..4 {
+                    a[row][j] -= f * a[col][j];
+                    inv[row][j] -= f * inv[col][j];
+                }
+            }
+        }
+        Some(Self(inv))
+    }
+
+    pub fn transform_point(&self, p: &Point3) -> Point3 {
+        let m = &self.0;
+        let x = m[0][0] * p[0] + m[0][1] * p[1] + m[0][2] * p[2] + m[0][3];
+        let y = m[1][0] * p[0] + m[1][1] * p[1] + m[1][2] * p[2] + m[1][3];
+        let z = m[2][0] * p[0] + m[2][1] * p[1] + m[2][2] * p[2] + m[2][3];
+        let w = m[3][0] * p[0] + m[3][1] * p[1] + m[3][2] * p[2] + m[3][3];
+        if w == 1.0 { Point3::new(x, y, z) } else { Point3::new(x, y, z) / w }
+    }
+
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.0;
+        Vec3::new(
+            m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+            m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+            m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2]
+        )
+    }
+}
+
+impl ops::Mul<Matrix4> for Matrix4 {
+    type Output = Self;
+    fn mul(self, rhs: Matrix4) -> Self::Output {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, x) in row.iter_mut().enumerate() {
+                *x = (0..4).map(|k| self.0[i][k] * rhs.0[k][j]).sum();
+            }
+        }
+        Self(m)
+    }
+}
+
+/// An affine transformation from object space to world space, stored alongside its inverse.
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    m: Matrix4,
+    inv: Matrix4
+}
+
+impl Default for Transform {
+    fn default() -> Self { Self::identity() }
+}
+
+impl Transform {
+    /// Panics if `m` is not invertible.
+    pub fn new(m: Matrix4) -> Self {
+        let inv = m.inverse().expect("Transform matrix must be invertible.");
+        Self { m, inv }
+    }
+
+    pub fn identity() -> Self { Self { m: Matrix4::identity(), inv: Matrix4::identity() } }
+
+    pub fn translate(delta: Vec3) -> Self {
+        let (x, y, z) = (delta.x(), delta.y(), delta.z());
+        Self {
+            m: Matrix4([
+                [1.0, 0.0, 0.0, x],
+                [0.0, 1.0, 0.0, y],
+                [0.0, 0.0, 1.0, z],
+                [0.0, 0.0, 0.0, 1.0],
+            ]),
+            inv: Matrix4([
+                [1.0, 0.0, 0.0, -x],
+                [0.0, 1.0, 0.0, -y],
+                [0.0, 0.0, 1.0, -z],
+                [0.0, 0.0, 0.0, 1.0],
+            ])
+        }
+    }
+
+    /// A (possibly non-uniform) scale about the origin.
+    pub fn scale(factors: Vec3) -> Self {
+        let (x, y, z) = (factors.x(), factors.y(), factors.z());
+        Self {
+            m: Matrix4([
+                [  x, 0.0, 0.0, 0.0],
+                [0.0,   y, 0.0, 0.0],
+                [0.0, 0.0,   z, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]),
+            inv: Matrix4([
+                [1.0 / x,     0.0,     0.0, 0.0],
+                [    0.0, 1.0 / y,     0.0, 0.0],
+                [    0.0,     0.0, 1.0 / z, 0.0],
+                [    0.0,     0.0,     0.0, 1.0],
+            ])
+        }
+    }
+
+    /// A counter-clockwise rotation of `degrees` about `axis`, which passes through the origin.
+    pub fn rotate(axis: Vec3, degrees: f32) -> Self {
+        let a = unit_vector(&axis);
+        let (sin, cos) = degrees_to_radians(degrees).sin_cos();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+
+        // Rodrigues' rotation formula:
+        let m = Matrix4([
+            [x * x + (1.0 - x * x) * cos, x * y * (1.0 - cos) - z * sin, x * z * (1.0 - cos) + y * sin, 0.0],
+            [x * y * (1.0 - cos) + z * sin, y * y + (1.0 - y * y) * cos, y * z * (1.0 - cos) - x * sin, 0.0],
+            [x * z * (1.0 - cos) - y * sin, y * z * (1.0 - cos) + x * sin, z * z + (1.0 - z * z) * cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        Self::from_rotation(m)
+    }
+
+    /// Builds a transform from a pure rotation matrix. These are orthogonal, so the inverse is
+    /// just the transpose.
+    pub fn from_rotation(m: Matrix4) -> Self { Self { m, inv: m.transpose() } }
+
+    /// Places an object at `from`, with its local +z axis pointing towards `to` and its local +y
+    /// axis as close to `up` as possible.
+    pub fn look_at(from: Point3, to: Point3, up: Vec3) -> Self {
+        let w = unit_vector(&(to - from));
+        let u = unit_vector(&cross(&up, &w));
+        let v = cross(&w, &u);
+
+        let m = Matrix4([
+            [u.x(), v.x(), w.x(), from.x()],
+            [u.y(), v.y(), w.y(), from.y()],
+            [u.z(), v.z(), w.z(), from.z()],
+            [  0.0,   0.0,   0.0,      1.0],
+        ]);
+        Self::new(m)
+    }
+
+    /// Returns the transform which first applies `self`, then `other`.
+    pub fn then(&self, other: &Transform) -> Self { *other * *self }
+
+    pub fn point(&self, p: &Point3) -> Point3 { self.m.transform_point(p) }
+    pub fn vector(&self, v: &Vec3) -> Vec3 { self.m.transform_vector(v) }
+
+    /// Transforms a surface normal, using the inverse transpose so that it stays perpendicular
+    /// to the surface under non-uniform scaling. The result is not normalised.
+    pub fn normal(&self, n: &Vec3) -> Vec3 { self.inv.transpose().transform_vector(n) }
+
+    /// Transforms a ray from world space into object space. The direction is not normalised, so
+    /// hit distances `t` are the same in both spaces.
+    pub fn ray_to_object(&self, r: &Ray) -> Ray {
+        Ray::new(self.inv.transform_point(&r.origin()), self.inv.transform_vector(&r.direction()), r.time())
+    }
+
+    pub fn bounding_box(&self, b: &Aabb) -> Aabb {
+        Aabb::from_points(&b.corners().map(|p| self.point(&p)))
+    }
+}
+
+impl ops::Mul<Transform> for Transform {
+    type Output = Self;
+    fn mul(self, rhs: Transform) -> Self::Output {
+        Self { m: self.m * rhs.m, inv: rhs.inv * self.inv }
+    }
+}
+
+/// Wraps some object, placing it in the world according to a `Transform`.
+///
+/// `H` may be an `Arc`, so that many instances can share one mesh (and its BVH).
+pub struct Transformed<H: Hittable> {
+    object: H,
+    transform: Transform
+}
+
+impl<H: Hittable> Transformed<H> {
+    pub fn new(object: H, transform: Transform) -> Self {
+        Self { object, transform }
+    }
+}
+
+impl<H: Hittable> Hittable for Transformed<H> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_transformed(&self.object, &self.transform, r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.object.bounding_box().map(|b| self.transform.bounding_box(&b))
+    }
+}
+
+impl<H: Solid> Solid for Transformed<H> {}
+
+/// Intersects `r` with `object` placed in the world by `transform`.
+pub fn hit_transformed(object: &impl Hittable, transform: &Transform, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    let local = transform.ray_to_object(r);
+    let mut rec = object.hit(&local, t_min, t_max)?;
+
+    // The normal's orientation relative to the ray is preserved by the transformation, so
+    // `front_face` is still valid:
+    rec.p = r.at(rec.t);
+    rec.normal = unit_vector(&transform.normal(&rec.normal));
+    if rec.tangent.length_squared() > 0.0 { rec.tangent = unit_vector(&transform.vector(&rec.tangent)); }
+    Some(rec)
+}
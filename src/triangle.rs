@@ -1,16 +1,35 @@
 use std::sync::Arc;
 
-use crate::{vec3::*, hittable::{Hittable, HitRecord}, material::Material};
+use crate::{aabb::Aabb, vec3::*, hittable::{Hittable, HitRecord}, material::Material};
 
 #[derive(Clone)]
 pub struct Triangle {
     points: [Point3; 3],
+    uvs: Option<[(f32, f32); 3]>,
     material: Arc<dyn Material>
 }
 
 impl Triangle {
     pub fn new(a: Point3, b: Point3, c: Point3, material: Arc<dyn Material>) -> Self {
-        Self { points: [a, b, c], material }
+        Self { points: [a, b, c], uvs: None, material }
+    }
+
+    /// Gives each point texture coordinates, which are interpolated across the triangle (rather
+    /// than reporting the barycentric coordinates of hits).
+    pub fn with_uvs(mut self, uvs: [(f32, f32); 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+
+    /// The direction in which `u` increases across the triangle.
+    fn tangent(&self) -> Vec3 {
+        let (e1, e2) = (self.points[1] - self.points[0], self.points[2] - self.points[0]);
+        let Some(uvs) = self.uvs else { return e1 };
+        let (du1, dv1) = (uvs[1].0 - uvs[0].0, uvs[1].1 - uvs[0].1);
+        let (du2, dv2) = (uvs[2].0 - uvs[0].0, uvs[2].1 - uvs[0].1);
+        let det = du1 * dv2 - du2 * dv1;
+        if det.abs() < 1e-12 { return e1 }
+        (dv2 * e1 - dv1 * e2) / det
     }
 
     fn normal(&self) -> Vec3 {
@@ -45,11 +64,27 @@ impl Hittable for Triangle {
         let pb = p - self.points[1];
         let pc = p - self.points[2];
 
-        if dot(&norm, &cross(&ab, &pa)) > 0.0 
-            && dot(&norm, &cross(&bc, &pb)) > 0.0 
-            && dot(&norm, &cross(&ca, &pc)) > 0.0 
-        {
-            Some(HitRecord::new(p, self.normal(), self.material.clone(), t, true))
+        let (ea, eb, ec) = (
+            dot(&norm, &cross(&bc, &pb)),
+            dot(&norm, &cross(&ca, &pc)),
+            dot(&norm, &cross(&ab, &pa))
+        );
+        if ea > 0.0 && eb > 0.0 && ec > 0.0 {
+            // Barycentric coordinates of the hit, relative to the 2nd and 3rd points:
+            let area = ea + eb + ec;
+            let mut rec = HitRecord::new(p, self.normal(), self.material.clone(), t, true);
+            (rec.u, rec.v) = (eb / area, ec / area);
+            if let Some(uvs) = self.uvs {
+                let w = [ea / area, eb / area, ec / area];
+                rec.u = (0..3).map(|i| w[i] * uvs[i].0).sum();
+                rec.v = (0..3).map(|i| w[i] * uvs[i].1).sum();
+            }
+            rec.tangent = self.tangent();
+            Some(rec)
         } else { None }   
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::from_points(&self.points))
+    }
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 // This is known as rtweekend.h in the text.
 
+use crate::rng::Rng;
 use crate::vec3::Colour;
 
 pub fn degrees_to_radians(deg: f32) -> f32 {
@@ -7,8 +8,8 @@ pub fn degrees_to_radians(deg: f32) -> f32 {
 }
 
 /// Returns a random double in the range from `min` up to, but **not including**, `max`.
-pub fn random_double(min: f32, max: f32) -> f32 {
-    min + (max - min) * fastrand::Rng::new().f32()
+pub fn random_double(rng: &mut Rng, min: f32, max: f32) -> f32 {
+    min + (max - min) * rng.next_f32()
 }
 
 pub fn throbber(x: usize) -> &'static str {
@@ -1,5 +1,6 @@
 use std::ops;
 
+use crate::rng::Rng;
 use crate::util::random_double;
 
 #[derive(Clone, Copy, Debug)]
@@ -21,8 +22,8 @@ impl Vec3 {
         self[0] * self[0] + self[1] * self[1] + self[2] * self[2]
     }
 
-    pub fn random(min: f32, max: f32) -> Self {
-        Vec3::new(random_double(min, max), random_double(min, max), random_double(min, max))
+    pub fn random(rng: &mut Rng, min: f32, max: f32) -> Self {
+        Vec3::new(random_double(rng, min, max), random_double(rng, min, max), random_double(rng, min, max))
     }
 
     pub fn near_zero(&self) -> bool {
@@ -138,15 +139,15 @@ pub fn unit_vector(v: &Vec3) -> Vec3 {
     *v / v.length()
 }
 
-pub fn random_in_unit_sphere() -> Vec3 {
+pub fn random_in_unit_sphere(rng: &mut Rng) -> Vec3 {
     loop {
-        let p = Vec3::random(-1.0, 1.0);
+        let p = Vec3::random(rng, -1.0, 1.0);
         if p.length_squared() < 1.0 { return p; }
     }
 }
 
-pub fn random_unit_vector() -> Vec3 {
-    unit_vector(&random_in_unit_sphere())
+pub fn random_unit_vector(rng: &mut Rng) -> Vec3 {
+    unit_vector(&random_in_unit_sphere(rng))
 }
 
 pub fn reflect(v: Vec3, n: Vec3) -> Vec3 {
@@ -160,9 +161,26 @@ pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: f32) -> Vec3 {
     r_out_parallel + r_out_perp
 }
 
-pub fn random_in_unit_disk() -> Vec3 {
-    loop {
-        let p = Vec3::new(random_double(-1.0, 1.0), random_double(-1.0, 1.0), 0.0);
-        if p.length_squared() < 1.0 { return p; }
-    }
-}
\ No newline at end of file
+/// Maps a 2D sample in [0, 1)² to a uniformly distributed direction.
+pub fn sample_unit_sphere((u1, u2): (f32, f32)) -> Vec3 {
+    let z = 1.0 - 2.0 * u1;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * std::f32::consts::PI * u2;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Maps a direction sample and a radius sample to a uniformly distributed point in the unit
+/// ball.
+pub fn sample_in_unit_ball(direction: (f32, f32), radius: f32) -> Vec3 {
+    radius.cbrt() * sample_unit_sphere(direction)
+}
+
+/// Maps a 2D sample in [0, 1)² to a uniformly distributed point on the unit disk (in the xy
+/// plane), using Shirley and Chiu's concentric mapping so that strata stay compact.
+pub fn sample_in_unit_disk((u1, u2): (f32, f32)) -> Vec3 {
+    let (a, b) = (2.0 * u1 - 1.0, 2.0 * u2 - 1.0);
+    if a == 0.0 && b == 0.0 { return Vec3::default() }
+    let quarter = std::f32::consts::FRAC_PI_4;
+    let (r, theta) = if a.abs() > b.abs() { (a, quarter * (b / a)) } else { (b, 2.0 * quarter - quarter * (a / b)) };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
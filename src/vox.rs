@@ -0,0 +1,159 @@
+use std::{fs, io};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::material::*;
+use crate::vec3::Colour;
+use crate::voxel::VoxelGrid;
+
+/// How MagicaVoxel says a palette entry should be rendered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VoxMaterial {
+    Diffuse,
+    Metal { roughness: f32 },
+    Glass { refractive_index: f32 },
+    Emit { strength: f32 }
+}
+
+/// One model from a `.vox` file: its size in voxels, and the `[x, y, z, colour index]` of each
+/// filled voxel.
+#[derive(Clone, Debug)]
+pub struct VoxModel {
+    pub size: [usize; 3],
+    pub voxels: Vec<[u8; 4]>
+}
+
+/// The contents of a MagicaVoxel `.vox` file.
+///
+/// Only the models, palette and materials are read; the scene graph (which positions several
+/// models relative to each other) is ignored. Colours and materials are indexed by the voxels'
+/// colour index, from 1 to 255.
+#[derive(Clone, Debug)]
+pub struct VoxFile {
+    pub models: Vec<VoxModel>,
+    pub palette: [Colour; 256],
+    pub materials: [VoxMaterial; 256]
+}
+
+fn invalid(msg: &str) -> io::Error { io::Error::new(io::ErrorKind::InvalidData, msg.to_string()) }
+
+/// Reads little-endian values from a byte slice, failing if it runs out.
+struct Reader<'a> { bytes: &'a [u8] }
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < n { return Err(invalid("Unexpected end of .vox file.")) }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn dict(&mut self) -> io::Result<HashMap<String, String>> {
+        let n = self.u32()?;
+        (0..n).map(|_| Ok((self.string()?, self.string()?))).collect()
+    }
+}
+
+/// Reads a `.vox` file. Files without a palette chunk (which rely on MagicaVoxel's built-in
+/// palette) are given a plain grey one instead.
+pub fn import(fp: &str) -> io::Result<VoxFile> {
+    parse(&fs::read(fp)?)
+}
+
+fn parse(bytes: &[u8]) -> io::Result<VoxFile> {
+    let mut r = Reader { bytes };
+    if r.take(4)? != b"VOX " { return Err(invalid("Not a .vox file.")) }
+    r.u32()?; // Version.
+
+    // The MAIN chunk holds everything else as its children:
+    if r.take(4)? != b"MAIN" { return Err(invalid("Missing MAIN chunk.")) }
+    let content = r.u32()? as usize;
+    r.u32()?;
+    r.take(content)?;
+
+    let mut file = VoxFile {
+        models: vec![],
+        palette: [Colour::new(0.75, 0.75, 0.75); 256],
+        materials: [VoxMaterial::Diffuse; 256]
+    };
+    let mut size = None;
+
+    while !r.bytes.is_empty() {
+        let id = r.take(4)?;
+        let content = r.u32()? as usize;
+        let children = r.u32()? as usize;
+        let mut chunk = Reader { bytes: r.take(content)? };
+        r.take(children)?;
+
+        match id {
+            b"SIZE" => size = Some([chunk.u32()?, chunk.u32()?, chunk.u32()?].map(|n| n as usize)),
+            b"XYZI" => {
+                let size = size.take().ok_or_else(|| invalid("XYZI chunk without a SIZE."))?;
+                let n = chunk.u32()? as usize;
+                let voxels = chunk.take(4 * n)?.chunks_exact(4).map(|v| [v[0], v[1], v[2], v[3]]).collect();
+                file.models.push(VoxModel { size, voxels });
+            }
+            b"RGBA" => {
+                // Entry `i` of the chunk is colour index `i + 1`:
+                for i in 0..255 {
+                    let c = chunk.take(4)?;
+                    file.palette[i + 1] = Colour::new(c[0] as f32, c[1] as f32, c[2] as f32) / 255.0;
+                }
+            }
+            b"MATL" => {
+                let index = chunk.u32()? as usize;
+                let props = chunk.dict()?;
+                let number = |key: &str, default: f32| props.get(key).and_then(|v| v.parse().ok()).unwrap_or(default);
+                let material = match props.get("_type").map(String::as_str) {
+                    Some("_metal") => VoxMaterial::Metal { roughness: number("_rough", 0.1) },
+                    // `_ior` is stored as one less than the actual refractive index:
+                    Some("_glass") => VoxMaterial::Glass { refractive_index: 1.0 + number("_ior", 0.5) },
+                    Some("_emit") => VoxMaterial::Emit { strength: number("_emit", 1.0) * (1.0 + number("_flux", 0.0)) },
+                    _ => VoxMaterial::Diffuse
+                };
+                if index < 256 { file.materials[index] = material; }
+            }
+            _ => {}
+        }
+    }
+
+    if file.models.is_empty() { return Err(invalid("No models in .vox file.")) }
+    Ok(file)
+}
+
+impl VoxFile {
+    /// Builds the material for each colour index, from its colour and `VoxMaterial`.
+    pub fn build_materials(&self) -> Vec<Arc<dyn Material>> {
+        (0..256).map(|i| -> Arc<dyn Material> {
+            let colour = self.palette[i];
+            match self.materials[i] {
+                VoxMaterial::Diffuse => Arc::new(Lambertian::new(colour)),
+                VoxMaterial::Metal { roughness } => Arc::new(Metal::new(colour, roughness)),
+                VoxMaterial::Glass { refractive_index } => Arc::new(Dielectric::new(refractive_index)),
+                VoxMaterial::Emit { strength } => Arc::new(DiffuseLight::new(strength * colour))
+            }
+        }).collect()
+    }
+
+    /// A grid of the voxels in model `index`, one unit per voxel, with z up as in MagicaVoxel.
+    pub fn grid(&self, index: usize) -> VoxelGrid {
+        let model = &self.models[index];
+        let [w, d, h] = model.size;
+        let mut cells = vec![0; w * d * h];
+        for &[x, y, z, c] in &model.voxels {
+            let (x, y, z) = (x as usize, y as usize, z as usize);
+            if x < w && y < d && z < h { cells[x + w * (y + d * z)] = c; }
+        }
+        VoxelGrid::new(model.size, cells, self.build_materials())
+    }
+}
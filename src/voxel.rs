@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::*;
+
+/// A grid of unit cubes spanning `(0, 0, 0)` to `size`, each either empty (0) or holding the
+/// index of its material.
+///
+/// Rays step through the grid one cell at a time (Amanatides and Woo's 3D-DDA), so the cost of a
+/// hit depends on how far the ray travels rather than on how many voxels there are. Surfaces lie
+/// wherever the material changes, so the ray may also start inside a voxel, e.g. after being
+/// refracted into glass.
+pub struct VoxelGrid {
+    size: [usize; 3],
+    cells: Vec<u8>,
+    materials: Vec<Arc<dyn Material>>
+}
+
+impl VoxelGrid {
+    /// `cells` is ordered with x varying fastest, then y, then z. Every non-zero cell must have a
+    /// corresponding entry in `materials`.
+    pub fn new(size: [usize; 3], cells: Vec<u8>, materials: Vec<Arc<dyn Material>>) -> Self {
+        assert_eq!(cells.len(), size[0] * size[1] * size[2]);
+        assert!(cells.iter().all(|&c| (c as usize) < materials.len()), "Voxel has no material.");
+        Self { size, cells, materials }
+    }
+
+    pub fn size(&self) -> [usize; 3] { self.size }
+
+    /// The material index of cell `(x, y, z)`, or 0 outside of the grid.
+    pub fn at(&self, cell: [i64; 3]) -> u8 {
+        if (0..3).any(|a| cell[a] < 0 || cell[a] >= self.size[a] as i64) { return 0 }
+        let [x, y, z] = cell.map(|c| c as usize);
+        self.cells[x + self.size[0] * (y + self.size[1] * z)]
+    }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::new(Point3::default(), Point3::new(self.size[0] as f32, self.size[1] as f32, self.size[2] as f32))
+    }
+
+    fn record(&self, r: &Ray, t: f32, axis: usize, outward: f32, index: u8) -> HitRecord {
+        let mut normal = Vec3::default();
+        normal[axis] = outward;
+
+        // Texture coordinates run across each face:
+        let p = r.at(t);
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut rec = HitRecord::new(p, normal, self.materials[index as usize].clone(), t, true);
+        rec.set_face_normal(r, &normal);
+        (rec.u, rec.v) = (p[a] - p[a].floor(), p[b] - p[b].floor());
+        rec
+    }
+}
+
+impl Hittable for VoxelGrid {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (t_enter, _) = self.bounds().interval(r, t_min, t_max)?;
+        let (orig, dir) = (r.origin(), r.direction());
+
+        // The cell containing the start of the ray, and the distances to each of its far faces:
+        let start = r.at(t_enter);
+        let mut cell = [0; 3];
+        let (mut step, mut t_next, mut t_delta) = ([0; 3], [f32::INFINITY; 3], [f32::INFINITY; 3]);
+        for a in 0..3 {
+            cell[a] = (start[a].floor() as i64).clamp(0, self.size[a] as i64 - 1);
+            if dir[a] > 0.0 {
+                step[a] = 1;
+                t_next[a] = ((cell[a] + 1) as f32 - orig[a]) / dir[a];
+                t_delta[a] = 1.0 / dir[a];
+            } else if dir[a] < 0.0 {
+                step[a] = -1;
+                t_next[a] = (cell[a] as f32 - orig[a]) / dir[a];
+                t_delta[a] = -1.0 / dir[a];
+            }
+        }
+
+        let mut current = self.at(cell);
+        if t_enter > t_min && current != 0 {
+            // The ray entered the grid straight into a voxel, through the face it crossed last:
+            let entry = |a: usize| match step[a] {
+                1 => -orig[a] / dir[a],
+                -1 => (self.size[a] as f32 - orig[a]) / dir[a],
+                _ => f32::NEG_INFINITY
+            };
+            let axis = (0..3).max_by(|&a, &b| entry(a).total_cmp(&entry(b)))?;
+            return Some(self.record(r, t_enter, axis, -(step[axis] as f32), current))
+        }
+
+        loop {
+            let axis = (0..3).min_by(|&a, &b| t_next[a].total_cmp(&t_next[b]))?;
+            let t = t_next[axis];
+            if t > t_max { return None }
+
+            cell[axis] += step[axis];
+            t_next[axis] += t_delta[axis];
+            let next = self.at(cell);
+            if next != current && t >= t_min {
+                return Some(if next != 0 {
+                    self.record(r, t, axis, -(step[axis] as f32), next)
+                } else {
+                    // Leaving a voxel for empty space:
+                    self.record(r, t, axis, step[axis] as f32, current)
+                })
+            }
+            if cell[axis] < 0 || cell[axis] >= self.size[axis] as i64 { return None }
+            current = next;
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> { Some(self.bounds()) }
+}